@@ -1,21 +1,37 @@
-fn char_to_dir(c: char) -> i32 {
-    match c {
-        '(' => 1,
-        ')' => -1,
-        _ => 0
-    }
-}
+use std::fs::File;
+
+mod trace;
+
+use trace::Trace;
+
+// most floors to list dwell times for
+const SHOW: usize = 10;
 
 fn main() -> anyhow::Result<()> {
-    let input = std::fs::read_to_string("input.txt")?;
-    let floor: i32 = input.chars().map(char_to_dir).sum();
+    // the instructions file, then any floors to say when santa first got to
+    let args = std::env::args().collect::<Vec<_>>();
+    let trace = Trace::read(File::open(args.get(1).map_or("input.txt", |p| p.as_str()))?)?;
+    let floors = args.iter().skip(2).map(|f| f.parse::<i64>()).collect::<Result<Vec<_>, _>>()?;
 
-    let basement_idx = input.chars().map(char_to_dir).scan(0, |floor, dir| {
-        *floor += dir;
-        Some(*floor)
-    }).enumerate().find(|x| x.1 == -1).expect("Basement never reached").0 + 1;
+    let basement = trace.first_reach(-1).map_or("never".to_string(), |p| p.to_string());
+    println!("Final Floor: {} Basement Reached at: {}", trace.floor, basement);
 
-    println!("Final Floor: {} Basement Reached at: {}", floor, basement_idx);
+    println!("{} instructions, lowest floor {} at {}, highest {} at {}",
+             trace.steps, trace.min.0, trace.min.1, trace.max.0, trace.max.1);
+    for floor in floors {
+        match trace.first_reach(floor) {
+            Some(p) => println!("Floor {} first reached at: {}", floor, p),
+            None => println!("Floor {} never reached", floor),
+        }
+    }
+    let mut dwell = trace.dwell.iter().collect::<Vec<_>>();
+    dwell.sort_by_key(|&(floor, &d)| (std::cmp::Reverse(d), floor));
+    println!("Most time spent on:");
+    for (floor, d) in dwell.iter().take(SHOW) {
+        println!("  floor {}: {}", floor, d);
+    }
+    println!("{} trips in or out of the basement, first few at {:?}",
+             trace.sign_changes.len(), &trace.sign_changes[..trace.sign_changes.len().min(SHOW)]);
 
     Ok(())
 }
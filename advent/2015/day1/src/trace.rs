@@ -0,0 +1,169 @@
+use std::io::{BufRead, BufReader, Read};
+
+use anyhow::{anyhow, Result};
+
+// Something kept for each floor visited. Floors only ever move one at a time so
+// the ones visited are always a run around 0, and a new one is always just past
+// either end.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Floors<T> {
+    // 0, 1, 2...
+    up: Vec<T>,
+    // -1, -2, -3...
+    down: Vec<T>,
+}
+
+impl<T> Floors<T> {
+    pub fn get(&self, floor: i64) -> Option<&T> {
+        if floor >= 0 {
+            self.up.get(floor as usize)
+        } else {
+            self.down.get((-floor - 1) as usize)
+        }
+    }
+
+    fn get_mut(&mut self, floor: i64) -> Option<&mut T> {
+        if floor >= 0 {
+            self.up.get_mut(floor as usize)
+        } else {
+            self.down.get_mut((-floor - 1) as usize)
+        }
+    }
+
+    fn push(&mut self, floor: i64, t: T) {
+        if floor >= 0 {
+            self.up.push(t);
+        } else {
+            self.down.push(t);
+        }
+    }
+
+    // lowest floor first
+    pub fn iter(&self) -> impl Iterator<Item = (i64, &T)> {
+        let down = self.down.iter().enumerate().rev().map(|(i, t)| (-(i as i64) - 1, t));
+        down.chain(self.up.iter().enumerate().map(|(i, t)| (i as i64, t)))
+    }
+}
+
+// Everything about where santa went. Positions count instructions from 1, the
+// start is position 0.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Trace {
+    pub steps: u64,
+    pub floor: i64,
+    // the lowest and highest floors and where they were first reached
+    pub min: (i64, u64),
+    pub max: (i64, u64),
+    pub first: Floors<u64>,
+    // how many positions, start included, were spent on each floor
+    pub dwell: Floors<u64>,
+    // where santa went into the basement or came back out of it
+    pub sign_changes: Vec<u64>,
+}
+
+impl Default for Trace {
+    fn default() -> Trace {
+        Trace {
+            steps: 0,
+            floor: 0,
+            min: (0, 0),
+            max: (0, 0),
+            first: Floors { up: vec![0], down: vec![] },
+            dwell: Floors { up: vec![1], down: vec![] },
+            sign_changes: vec![],
+        }
+    }
+}
+
+impl Trace {
+    pub fn step(&mut self, up: bool) {
+        let was = self.floor;
+        self.steps += 1;
+        self.floor += if up { 1 } else { -1 };
+        match self.dwell.get_mut(self.floor) {
+            Some(dwell) => *dwell += 1,
+            None => {
+                self.first.push(self.floor, self.steps);
+                self.dwell.push(self.floor, 1);
+                if self.floor > self.max.0 {
+                    self.max = (self.floor, self.steps);
+                } else {
+                    self.min = (self.floor, self.steps);
+                }
+            },
+        }
+        if (was < 0) != (self.floor < 0) {
+            self.sign_changes.push(self.steps);
+        }
+    }
+
+    // Reads instructions a buffer at a time so the whole thing never has to
+    // fit in memory. Whitespace is skipped, anything else is an error.
+    pub fn read(input: impl Read) -> Result<Trace> {
+        let mut trace = Trace::default();
+        let mut input = BufReader::with_capacity(1 << 16, input);
+        let mut offset = 0;
+        loop {
+            let buf = input.fill_buf()?;
+            if buf.is_empty() {
+                break;
+            }
+            for (i, &b) in buf.iter().enumerate() {
+                match b {
+                    b'(' => trace.step(true),
+                    b')' => trace.step(false),
+                    b if b.is_ascii_whitespace() => (),
+                    _ => return Err(anyhow!("bad instruction {:?} at byte {}", b as char, offset + i)),
+                }
+            }
+            let len = buf.len();
+            offset += len;
+            input.consume(len);
+        }
+        Ok(trace)
+    }
+
+    pub fn first_reach(&self, floor: i64) -> Option<u64> {
+        self.first.get(floor).copied()
+    }
+}
+
+#[cfg(test)]
+fn trace(s: &str) -> Trace {
+    Trace::read(s.as_bytes()).unwrap()
+}
+
+#[test]
+fn test_examples() {
+    for (s, floor) in [("(())", 0), ("()()", 0), ("(((", 3), ("(()(()(", 3), ("))(((((", 3), ("())", -1), ("))(", -1), (")))", -3), (")())())", -3)].iter() {
+        assert_eq!(trace(s).floor, *floor, "{}", s);
+    }
+    assert_eq!(trace(")").first_reach(-1), Some(1));
+    assert_eq!(trace("()())").first_reach(-1), Some(5));
+    assert_eq!(trace("(((").first_reach(-1), None);
+}
+
+#[test]
+fn test_trace() {
+    let t = trace("(()))))(\n((((");
+    assert_eq!((t.steps, t.floor), (12, 2));
+    assert_eq!((t.min, t.max), ((-3, 7), (2, 2)));
+    assert_eq!(t.first.iter().collect::<Vec<_>>(), vec![(-3, &7), (-2, &6), (-1, &5), (0, &0), (1, &1), (2, &2)]);
+    assert_eq!(t.dwell.iter().collect::<Vec<_>>(), vec![(-3, &1), (-2, &2), (-1, &2), (0, &3), (1, &3), (2, &2)]);
+    assert_eq!(t.dwell.iter().map(|(_, d)| d).sum::<u64>(), t.steps + 1);
+    assert_eq!(t.sign_changes, vec![5, 10]);
+    assert!(Trace::read("(()x".as_bytes()).unwrap_err().to_string().contains("byte 3"));
+}
+
+#[test]
+fn test_stream() {
+    // way more than one buffer's worth, up then back down past the start
+    let n = 3_000_000;
+    let input = std::io::repeat(b'(').take(n).chain(std::io::repeat(b')').take(n + 2));
+    let t = Trace::read(input).unwrap();
+    assert_eq!((t.floor, t.max, t.min), (-2, (n as i64, n), (-2, 2 * n + 2)));
+    assert_eq!(t.first_reach(1000), Some(1000));
+    assert_eq!(t.first_reach(-1), Some(2 * n + 1));
+    assert_eq!(t.dwell.get(5), Some(&2));
+    assert_eq!(t.sign_changes, vec![2 * n + 1]);
+}
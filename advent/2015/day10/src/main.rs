@@ -1,12 +1,118 @@
-#![feature(slice_group_by)]
+use anyhow::{anyhow, Result};
 
-use itertools::unfold;
+// Conway's elements: after enough rounds every look and say string is a row of these
+// and each one decays into the same elements no matter what's around it, so it's
+// enough to count how many of each there are. Name, digits, what it decays into.
+const ELEMENTS: [(&str, &str, &str); 92] = [
+    ("H", "22", "H"),
+    ("He", "13112221133211322112211213322112", "Hf.Pa.H.Ca.Li"),
+    ("Li", "312211322212221121123222112", "He"),
+    ("Be", "111312211312113221133211322112211213322112", "Ge.Ca.Li"),
+    ("B", "1321132122211322212221121123222112", "Be"),
+    ("C", "3113112211322112211213322112", "B"),
+    ("N", "111312212221121123222112", "C"),
+    ("O", "132112211213322112", "N"),
+    ("F", "31121123222112", "O"),
+    ("Ne", "111213322112", "F"),
+    ("Na", "123222112", "Ne"),
+    ("Mg", "3113322112", "Pm.Na"),
+    ("Al", "1113222112", "Mg"),
+    ("Si", "1322112", "Al"),
+    ("P", "311311222112", "Ho.Si"),
+    ("S", "1113122112", "P"),
+    ("Cl", "132112", "S"),
+    ("Ar", "3112", "Cl"),
+    ("K", "1112", "Ar"),
+    ("Ca", "12", "K"),
+    ("Sc", "3113112221133112", "Ho.Pa.H.Ca.Co"),
+    ("Ti", "11131221131112", "Sc"),
+    ("V", "13211312", "Ti"),
+    ("Cr", "31132", "V"),
+    ("Mn", "111311222112", "Cr.Si"),
+    ("Fe", "13122112", "Mn"),
+    ("Co", "32112", "Fe"),
+    ("Ni", "11133112", "Zn.Co"),
+    ("Cu", "131112", "Ni"),
+    ("Zn", "312", "Cu"),
+    ("Ga", "13221133122211332", "Eu.Ca.Ac.H.Ca.Zn"),
+    ("Ge", "31131122211311122113222", "Ho.Ga"),
+    ("As", "11131221131211322113322112", "Ge.Na"),
+    ("Se", "13211321222113222112", "As"),
+    ("Br", "3113112211322112", "Se"),
+    ("Kr", "11131221222112", "Br"),
+    ("Rb", "1321122112", "Kr"),
+    ("Sr", "3112112", "Rb"),
+    ("Y", "1112133", "Sr.U"),
+    ("Zr", "12322211331222113112211", "Y.H.Ca.Tc"),
+    ("Nb", "1113122113322113111221131221", "Er.Zr"),
+    ("Mo", "13211322211312113211", "Nb"),
+    ("Tc", "311322113212221", "Mo"),
+    ("Ru", "132211331222113112211", "Eu.Ca.Tc"),
+    ("Rh", "311311222113111221131221", "Ho.Ru"),
+    ("Pd", "111312211312113211", "Rh"),
+    ("Ag", "132113212221", "Pd"),
+    ("Cd", "3113112211", "Ag"),
+    ("In", "11131221", "Cd"),
+    ("Sn", "13211", "In"),
+    ("Sb", "3112221", "Pm.Sn"),
+    ("Te", "1322113312211", "Eu.Ca.Sb"),
+    ("I", "311311222113111221", "Ho.Te"),
+    ("Xe", "11131221131211", "I"),
+    ("Cs", "13211321", "Xe"),
+    ("Ba", "311311", "Cs"),
+    ("La", "11131", "Ba"),
+    ("Ce", "1321133112", "La.H.Ca.Co"),
+    ("Pr", "31131112", "Ce"),
+    ("Nd", "111312", "Pr"),
+    ("Pm", "132", "Nd"),
+    ("Sm", "311332", "Pm.Ca.Zn"),
+    ("Eu", "1113222", "Sm"),
+    ("Gd", "13221133112", "Eu.Ca.Co"),
+    ("Tb", "3113112221131112", "Ho.Gd"),
+    ("Dy", "111312211312", "Tb"),
+    ("Ho", "1321132", "Dy"),
+    ("Er", "311311222", "Ho.Pm"),
+    ("Tm", "11131221133112", "Er.Ca.Co"),
+    ("Yb", "1321131112", "Tm"),
+    ("Lu", "311312", "Yb"),
+    ("Hf", "11132", "Lu"),
+    ("Ta", "13112221133211322112211213322113", "Hf.Pa.H.Ca.W"),
+    ("W", "312211322212221121123222113", "Ta"),
+    ("Re", "111312211312113221133211322112211213322113", "Ge.Ca.W"),
+    ("Os", "1321132122211322212221121123222113", "Re"),
+    ("Ir", "3113112211322112211213322113", "Os"),
+    ("Pt", "111312212221121123222113", "Ir"),
+    ("Au", "132112211213322113", "Pt"),
+    ("Hg", "31121123222113", "Au"),
+    ("Tl", "111213322113", "Hg"),
+    ("Pb", "123222113", "Tl"),
+    ("Bi", "3113322113", "Pm.Pb"),
+    ("Po", "1113222113", "Bi"),
+    ("At", "1322113", "Po"),
+    ("Rn", "311311222113", "Ho.At"),
+    ("Fr", "1113122113", "Rn"),
+    ("Ra", "132113", "Fr"),
+    ("Ac", "3113", "Ra"),
+    ("Th", "1113", "Ac"),
+    ("Pa", "13", "Th"),
+    ("U", "3", "Pa"),
+];
+
+// how many rounds to do by hand before giving up on splitting the start into elements
+const WARMUP: usize = 20;
+// how many rounds a split has to agree with the real thing before it's believed
+const CHECK: usize = 10;
+// how many complete splits get checked before giving up on one
+const TRIES: usize = 100;
 
 fn look_and_say(s: &[u8]) -> Vec<u8> {
     let mut res: Vec<u8> = vec![];
-    for run in s.group_by(|a, b| *a == *b) {
-        res.push(run.len() as u8);
-        res.push(run[0]);
+    let mut i = 0;
+    while i < s.len() {
+        let run = s[i..].iter().take_while(|&&d| d == s[i]).count();
+        res.push(run as u8);
+        res.push(s[i]);
+        i += run;
     }
 
     res
@@ -14,17 +120,192 @@ fn look_and_say(s: &[u8]) -> Vec<u8> {
 
 #[test]
 fn test_lns() {
-    assert_eq!(look_and_say(&vec![1]), vec![1, 1]);
-    assert_eq!(look_and_say(&vec![1, 1]), vec![2, 1]);
-    assert_eq!(look_and_say(&vec![2, 1]), vec![1, 2, 1, 1]);
+    assert_eq!(look_and_say(&[1]), vec![1, 1]);
+    assert_eq!(look_and_say(&[1, 1]), vec![2, 1]);
+    assert_eq!(look_and_say(&[2, 1]), vec![1, 2, 1, 1]);
+
+}
+
+// "Hf.Pa.H" to indexes into ELEMENTS
+fn elements(names: &str) -> Result<Vec<usize>> {
+    names.split('.').map(|name| ELEMENTS.iter().position(|e| e.0 == name).ok_or_else(|| {
+        anyhow!("unknown element {:?}, should be one of {}", name, ELEMENTS.map(|e| e.0).join(" "))
+    })).collect()
+}
+
+struct Table {
+    digits: Vec<Vec<u8>>,
+    decay: Vec<Vec<usize>>,
+}
+
+impl Table {
+    fn new() -> Result<Table> {
+        Ok(Table {
+            digits: ELEMENTS.iter().map(|e| e.1.bytes().map(|b| b - b'0').collect()).collect(),
+            decay: ELEMENTS.iter().map(|e| elements(e.2)).collect::<Result<_>>()?,
+        })
+    }
+
+    // digits like 3113322113, or elements like Pb or Hf.Pa.H.Ca.Li
+    fn start(&self, s: &str) -> Result<Vec<u8>> {
+        if !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()) {
+            return Ok(s.bytes().map(|b| b - b'0').collect());
+        }
+        Ok(elements(s)?.into_iter().flat_map(|e| self.digits[e].iter().copied()).collect())
+    }
+
+    // Splits s into elements, if it is made of them. A row of elements only really
+    // splits if the boundaries don't interact, so the split has to keep agreeing
+    // with what s actually turns into for a while.
+    fn split(&self, s: &[u8]) -> Option<Vec<usize>> {
+        // ends[at] is whether s[at..] can be cut into elements at all, so the
+        // search never goes down a branch that can't reach the end
+        let mut ends = vec![false; s.len() + 1];
+        ends[s.len()] = true;
+        for at in (0..s.len()).rev() {
+            ends[at] = self.digits.iter().any(|d| s[at..].starts_with(d) && ends[at + d.len()]);
+        }
+        let mut parts = vec![];
+        let mut tries = TRIES;
+        self.split_from(s, 0, &ends, &mut parts, &mut tries).then_some(parts)
+    }
+
+    fn split_from(&self, s: &[u8], at: usize, ends: &[bool], parts: &mut Vec<usize>, tries: &mut usize) -> bool {
+        if at == s.len() {
+            *tries -= 1;
+            return self.holds(s, parts);
+        }
+        for (e, digits) in self.digits.iter().enumerate() {
+            if *tries == 0 {
+                return false;
+            }
+            if s[at..].starts_with(digits) && ends[at + digits.len()] {
+                parts.push(e);
+                if self.split_from(s, at + digits.len(), ends, parts, tries) {
+                    return true;
+                }
+                parts.pop();
+            }
+        }
+        false
+    }
+
+    fn holds(&self, s: &[u8], parts: &[usize]) -> bool {
+        let mut s = s.to_vec();
+        let mut parts = parts.to_vec();
+        for _ in 0..CHECK {
+            s = look_and_say(&s);
+            parts = parts.iter().flat_map(|&p| self.decay[p].iter().copied()).collect();
+            if !parts.iter().flat_map(|&p| self.digits[p].iter().copied()).eq(s.iter().copied()) {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn step(&self, counts: &[u128]) -> Option<Vec<u128>> {
+        let mut next = vec![0u128; counts.len()];
+        for (e, &count) in counts.iter().enumerate() {
+            for &d in &self.decay[e] {
+                next[d] = next[d].checked_add(count)?;
+            }
+        }
+        Some(next)
+    }
 
+    fn len(&self, counts: &[u128]) -> Option<u128> {
+        counts.iter().zip(&self.digits).try_fold(0u128, |total, (&count, digits)| {
+            total.checked_add(count.checked_mul(digits.len() as u128)?)
+        })
+    }
+
+    // length after n rounds, None once it doesn't fit in a u128 (somewhere past 330)
+    fn length(&self, start: &[u8], rounds: usize) -> Option<u128> {
+        let mut s = start.to_vec();
+        for done in 0..=WARMUP.min(rounds) {
+            if let Some(parts) = self.split(&s) {
+                let mut counts = vec![0u128; self.digits.len()];
+                for p in parts {
+                    counts[p] += 1;
+                }
+                for _ in done..rounds {
+                    counts = self.step(&counts)?;
+                }
+                return self.len(&counts);
+            }
+            if done == rounds {
+                break;
+            }
+            s = look_and_say(&s);
+        }
+        // never settled into elements, so do it the slow way
+        let mut s = start.to_vec();
+        for _ in 0..rounds {
+            s = look_and_say(&s);
+        }
+        Some(s.len() as u128)
+    }
+}
+
+#[test]
+fn test_table() {
+    let table = Table::new().unwrap();
+    for (e, digits) in table.digits.iter().enumerate() {
+        let decayed = table.decay[e].iter().flat_map(|&d| table.digits[d].clone()).collect::<Vec<_>>();
+        assert_eq!(look_and_say(digits), decayed, "{}", ELEMENTS[e].0);
+    }
 }
 
-fn main() {
-    let res = unfold(vec![3, 1, 1, 3, 3, 2, 2, 1, 1, 3], |v| {
-        *v = look_and_say(&v);
-        Some(v.clone())
-    }).take(50).last().unwrap();
+#[test]
+fn test_length() {
+    let table = Table::new().unwrap();
+    for start in [vec![1], vec![3, 1, 1, 3, 3, 2, 2, 1, 1, 3], vec![1, 1, 1, 3, 1, 2, 2, 1, 1, 3], vec![2, 2, 2, 1]] {
+        let mut s = start.clone();
+        for rounds in 0..=30 {
+            assert_eq!(table.length(&start, rounds), Some(s.len() as u128), "{:?} {}", start, rounds);
+            s = look_and_say(&s);
+        }
+    }
+    assert_eq!(table.split(&[3, 1, 1, 3, 3, 2, 2, 1, 1, 3]), Some(vec![82]));
+    assert_eq!(table.length(&[1], 1000), None);
+}
+
+#[test]
+fn test_no_elements() {
+    // nothing ends in a 4, so these never split and every warmup round has to
+    // rule out all the ways of cutting up everything before it
+    let table = Table::new().unwrap();
+    for start in [vec![3, 1, 1, 3, 3, 2, 2, 1, 1, 3, 4], vec![1, 1, 1, 3, 1, 2, 2, 1, 1, 3, 1, 1, 1, 3, 1, 2, 2, 1, 1, 3, 4]] {
+        let mut s = start.clone();
+        for rounds in 0..=WARMUP + 5 {
+            assert_eq!(table.length(&start, rounds), Some(s.len() as u128), "{:?} {}", start, rounds);
+            s = look_and_say(&s);
+        }
+        assert_eq!(table.split(&s), None);
+    }
+}
+
+#[test]
+fn test_start() {
+    let table = Table::new().unwrap();
+    assert_eq!(table.start("3113322113").unwrap(), vec![3, 1, 1, 3, 3, 2, 2, 1, 1, 3]);
+    assert_eq!(table.start("Pb").unwrap(), vec![1, 2, 3, 2, 2, 2, 1, 1, 3]);
+    assert_eq!(table.start("Pm.U").unwrap(), vec![1, 3, 2, 3]);
+    let err = table.start("Pb.Xx").unwrap_err().to_string();
+    assert!(err.starts_with("unknown element \"Xx\", should be one of H He Li") && err.ends_with("Pa U"), "{}", err);
+    assert!(table.start("").is_err());
+}
+
+fn main() -> Result<()> {
+    // how many rounds, and what to start from as digits or elements
+    let args = std::env::args().collect::<Vec<_>>();
+    let rounds = args.get(1).map_or(Ok(50), |r| r.parse().map_err(|_| anyhow!("rounds should be a number, not {:?}", r)))?;
+    let table = Table::new()?;
+    let start = table.start(args.get(2).map_or("3113322113", |s| s.as_str()))?;
+    match table.length(&start, rounds) {
+        Some(len) => println!("len = {}", len),
+        None => println!("len doesn't fit in a u128 after {} rounds", rounds),
+    }
 
-    println!("len = {}", res.len());
+    Ok(())
 }
@@ -1,14 +1,26 @@
+use anyhow::{anyhow, Result};
 use itertools::Itertools;
+#[cfg(test)]
 use std::iter::successors;
 
 fn ascii_to_n(d: char) -> u64 {
     (d as u8 - 97) as u64
 }
 
+// passwords and banned letters can only be a to z
+fn letters(s: &str) -> Result<Vec<u8>> {
+    s.chars().map(|c| match c {
+        'a'..='z' => Ok(ascii_to_n(c) as u8),
+        _ => Err(anyhow!("{:?} in {:?} isn't a lowercase letter", c, s)),
+    }).collect()
+}
+
+#[cfg(test)]
 fn n_to_ascii(n: u64) -> char {
     (n as u8 + 97) as char
 }
 
+#[cfg(test)]
 fn parse(s: &str) -> u64 {
     let mut n = 0;
     for (i, c) in s.chars().rev().enumerate() {
@@ -24,12 +36,13 @@ fn test_parse() {
     assert_eq!(parse("bb"), 27);
 }
 
+#[cfg(test)]
 fn unparse(n: u64) -> String {
     let mut s = Vec::new();
     let mut n = n;
     while n > 0 {
         let rem = n % 26;
-        n = n / 26;
+        n /= 26;
 
         s.push(n_to_ascii(rem))
     }
@@ -44,47 +57,221 @@ fn test_unparse() {
     assert_eq!(unparse(27), "bb");
 }
 
+// the old way, only kept around to check the generator against
+#[cfg(test)]
 fn inc(s: &str) -> String {
     unparse(parse(s) + 1)
 }
 
-fn separate_pairs(s: &str) -> bool {
-    let mut found_pair = None;
-    for (a, _b) in s.chars().tuple_windows().filter(|(a, b)| a == b) {
-        match found_pair {
-            Some(f) if f != a => {
-                return true
-            },
-            None => {
-                found_pair = Some(a);
-            },
-            _ => {}
+// Passwords are worked on as letters 0..26. check is the real test for a whole
+// password, feasible says whether some password of length len could still start
+// with prefix. feasible can be optimistic, but the closer it is the less searching.
+trait Rule {
+    fn check(&self, pw: &[u8]) -> bool;
+    fn feasible(&self, prefix: &[u8], len: usize) -> bool;
+}
+
+struct Banned(Vec<u8>);
+
+impl Rule for Banned {
+    fn check(&self, pw: &[u8]) -> bool {
+        !pw.iter().any(|c| self.0.contains(c))
+    }
+
+    fn feasible(&self, prefix: &[u8], _len: usize) -> bool {
+        self.check(prefix)
+    }
+}
+
+// at least n pairs, each of a different letter
+struct Pairs(usize);
+
+fn doubled(pw: &[u8]) -> Vec<u8> {
+    pw.iter().tuple_windows().filter(|(a, b)| a == b).map(|(a, _)| *a).unique().collect()
+}
+
+impl Rule for Pairs {
+    fn check(&self, pw: &[u8]) -> bool {
+        doubled(pw).len() >= self.0
+    }
+
+    fn feasible(&self, prefix: &[u8], len: usize) -> bool {
+        let have = doubled(prefix);
+        let left = len - prefix.len();
+        // the last letter can pair up with just one more
+        let more = match prefix.last() {
+            Some(last) if left > 0 && !have.contains(last) => 1 + (left - 1) / 2,
+            _ => left / 2,
+        };
+        have.len() + more >= self.0
+    }
+}
+
+// a run of n letters going up by one, like abc
+struct Straight(usize);
+
+// length of the increasing run ending at the end of pw, and whether a full one showed up
+fn straight_run(pw: &[u8], n: usize) -> (usize, bool) {
+    let mut run = 0;
+    for (i, c) in pw.iter().enumerate() {
+        run = if i > 0 && pw[i - 1] + 1 == *c { run + 1 } else { 1 };
+        if run >= n {
+            return (run, true);
+        }
+    }
+    (run, false)
+}
+
+impl Rule for Straight {
+    fn check(&self, pw: &[u8]) -> bool {
+        straight_run(pw, self.0).1
+    }
+
+    fn feasible(&self, prefix: &[u8], len: usize) -> bool {
+        let (run, found) = straight_run(prefix, self.0);
+        let left = len - prefix.len();
+        let need = self.0 - run;
+        found || left >= self.0 || (left >= need && prefix.last().is_some_and(|&c| c as usize + need < 26))
+    }
+}
+
+struct Policy {
+    rules: Vec<Box<dyn Rule>>,
+}
+
+impl Policy {
+    fn santa() -> Policy {
+        Policy {
+            rules: vec![
+                Box::new(Straight(3)),
+                Box::new(Banned(vec![8, 14, 11])),
+                Box::new(Pairs(2)),
+            ],
+        }
+    }
+
+    fn valid(&self, pw: &[u8]) -> bool {
+        self.rules.iter().all(|r| r.check(pw))
+    }
+
+    fn feasible(&self, prefix: &[u8], len: usize) -> bool {
+        self.rules.iter().all(|r| r.feasible(prefix, len))
+    }
+
+    // smallest way to finish buf off to len letters
+    fn fill(&self, buf: &mut Vec<u8>, len: usize) -> bool {
+        if buf.len() == len {
+            return self.valid(buf);
         }
+        for c in 0..26 {
+            buf.push(c);
+            if self.feasible(buf, len) && self.fill(buf, len) {
+                return true;
+            }
+            buf.pop();
+        }
+        false
     }
-    false
+
+    // The smallest valid password after pw. Bump the rightmost letter that leaves a
+    // workable prefix and build the rest up from scratch. Anything after a banned
+    // letter never has a workable prefix, so that letter is the first to get bumped.
+    fn next(&self, pw: &str) -> Result<Option<String>> {
+        let pw = letters(pw)?;
+        let len = pw.len();
+        let ok = (0..=len).take_while(|&i| self.feasible(&pw[..i], len)).count();
+        for i in (0..ok.min(len)).rev() {
+            for c in pw[i] + 1..26 {
+                let mut buf = pw[..i].to_vec();
+                buf.push(c);
+                if self.feasible(&buf, len) && self.fill(&mut buf, len) {
+                    return Ok(Some(buf.iter().map(|&c| (c + b'a') as char).collect()));
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[test]
+fn test_rules() {
+    let pw = |s: &str| s.chars().map(|c| ascii_to_n(c) as u8).collect::<Vec<_>>();
+    let santa = Policy::santa();
+    assert!(!santa.valid(&pw("hijklmmn")));
+    assert!(!santa.valid(&pw("abbceffg")));
+    assert!(!santa.valid(&pw("abbcegjk")));
+    assert!(santa.valid(&pw("abcdffaa")));
+    assert!(!santa.valid(&pw("abcdfaaa")));
+
+    assert!(Straight(3).feasible(&pw("xab"), 5));
+    assert!(Straight(3).feasible(&pw("xy"), 3));
+    assert!(!Straight(3).feasible(&pw("yz"), 3));
+    assert!(!Straight(4).feasible(&pw("ab"), 3));
+    assert!(Pairs(2).feasible(&pw("ab"), 5));
+    assert!(!Pairs(2).feasible(&pw("abc"), 5));
+    assert!(Pairs(2).feasible(&pw("abb"), 5));
+}
+
+#[test]
+fn test_next() {
+    let santa = Policy::santa();
+    assert_eq!(santa.next("abcdefgh").unwrap().as_deref(), Some("abcdffaa"));
+    assert_eq!(santa.next("ghijklmn").unwrap().as_deref(), Some("ghjaabcc"));
+    assert_eq!(santa.next("zzzzzzzz").unwrap(), None);
+    assert!(santa.next("abcDefgh").is_err());
+    assert!(santa.next("ab{defgh").is_err());
+
+    // longer straights and a different set of banned letters
+    let policy = Policy { rules: vec![Box::new(Straight(4)), Box::new(Banned(vec![0, 1])), Box::new(Pairs(1))] };
+    assert_eq!(policy.next("aaaaaa").unwrap().as_deref(), Some("cccdef"));
 }
 
-fn increasing_straight(s: &str) -> bool {
-    s.chars().tuple_windows().find(|(a, b, c)| {
-        let a = ascii_to_n(*a) + 2;
-        let b = ascii_to_n(*b) + 1;
-        let c = ascii_to_n(*c);
-        a == b && b == c
-    }).is_some()
+#[test]
+fn test_against_brute_force() {
+    // a looser policy than santa's, so the brute force finds something quickly
+    let policy = Policy { rules: vec![Box::new(Straight(3)), Box::new(Banned(vec![8, 14, 11])), Box::new(Pairs(1))] };
+    for start in ["bzzzy", "ghiab", "xyzzy", "mnopq", "hhhhh"] {
+        let brute = successors(Some(inc(start)), |s| Some(inc(s)))
+            .take_while(|s| s.len() == start.len())
+            .find(|s| policy.valid(&s.chars().map(|c| ascii_to_n(c) as u8).collect::<Vec<_>>()));
+        assert_eq!(policy.next(start).unwrap(), brute, "{}", start);
+    }
 }
 
-fn no_confusing(s: &str) -> bool {
-    s.chars().find(|&c| c == 'i' || c == 'o' || c == 'l').is_none()
+// a straight can't be longer than the alphabet
+fn straight(s: &str) -> Result<Straight> {
+    match s.parse() {
+        Ok(n @ 1..=26) => Ok(Straight(n)),
+        _ => Err(anyhow!("straight length should be a number from 1 to 26, not {:?}", s)),
+    }
 }
 
-fn valid(s: &str) -> bool {
-    separate_pairs(s) && increasing_straight(s) && no_confusing(s)
+#[test]
+fn test_args() {
+    assert_eq!(straight("4").unwrap().0, 4);
+    assert!(straight("0").is_err());
+    assert!(straight("27").is_err());
+    assert!(straight("three").is_err());
+    assert_eq!(letters("ilo").unwrap(), vec![8, 11, 14]);
+    assert!(letters("i,o").is_err());
 }
 
+fn main() -> Result<()> {
+    let args = std::env::args().collect::<Vec<_>>();
+    let start = args.get(1).map_or("cqjxjnds", |s| s.as_str());
+    let mut policy = Policy::santa();
+    if let Some(n) = args.get(2) {
+        policy.rules[0] = Box::new(straight(n)?);
+    }
+    if let Some(banned) = args.get(3) {
+        policy.rules[1] = Box::new(Banned(letters(banned)?));
+    }
 
-fn main() {
-    let next = successors(Some("cqjxjnds".to_string()), |s| Some(inc(s))).
-        filter(|s| valid(s)).nth(1).unwrap();
+    let mut pw = start.to_string();
+    for _ in 0..2 {
+        pw = policy.next(&pw)?.ok_or_else(|| anyhow!("no valid password comes after {}", pw))?;
+        println!("{}", pw);
+    }
 
-    println!("{}", next);
+    Ok(())
 }
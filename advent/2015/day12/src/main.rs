@@ -1,10 +1,20 @@
+mod stream;
+
+use std::{fs::File, io::BufReader};
+
 use anyhow::Result;
-use serde_json::{from_str, Map, Value};
+#[cfg(test)]
+use serde_json::{Map, Value};
 
+use stream::{Walker, parse_rules};
+
+// the original in-memory version, kept to check the streaming one against
+#[cfg(test)]
 fn has_red(m: &Map<String, Value>) -> bool {
     m.values().any(|s| s == "red")
 }
 
+#[cfg(test)]
 fn sum_nums(json: &Value) -> f64 {
     use serde_json::Value::*;
     match json {
@@ -21,9 +31,29 @@ fn test_sum_nums() {
     assert_eq!(sum_nums(&json!([1,{"c":"red","b":2},3])), 4f64);
 }
 
+#[test]
+fn test_streaming_matches() {
+    let input = include_str!("input.txt");
+    let rules = parse_rules("object with red").unwrap();
+    let streamed = Walker::new(input.as_bytes(), &rules, 0).sum().unwrap();
+    assert_eq!(streamed, sum_nums(&serde_json::from_str(input).unwrap()));
+}
+
+// day12 [file] [rules] [depth]
 fn main() -> Result<()> {
-    let json = from_str(include_str!("input.txt"))?;
-    println!("sum: {}", sum_nums(&json));
+    let args = std::env::args().collect::<Vec<_>>();
+    let path = args.get(1).map_or("src/input.txt", |p| p.as_str());
+    let rules = parse_rules(args.get(2).map_or("object with red", |r| r.as_str()))?;
+    let depth = args.get(3).map_or(Ok(0), |d| d.parse())?;
+
+    let mut walker = Walker::new(BufReader::new(File::open(path)?), &rules, depth);
+    let sum = walker.sum()?;
+    if depth > 0 {
+        for sub in &walker.subtotals {
+            println!("{}: {}{}", sub.path, sub.sum, if sub.skipped { " (skipped)" } else { "" });
+        }
+    }
+    println!("sum: {}", sum);
 
     Ok(())
 }
@@ -0,0 +1,406 @@
+use std::{fmt::Write, io::BufRead, str::FromStr};
+
+use anyhow::{Result, anyhow};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Kind {
+    Object,
+    Array,
+    Any,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Test {
+    // one of the container's own values is this
+    With,
+    // the container is the value of this key
+    Under,
+}
+
+// <object|array|any> <with|under> <value>, e.g. "object with red" or "array under items"
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rule {
+    kind: Kind,
+    test: Test,
+    value: String,
+    // a quoted value only matches strings, so "1" can mean the string and not the number
+    quoted: bool,
+}
+
+impl FromStr for Rule {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let words = s.split_whitespace().collect::<Vec<_>>();
+        let [kind, test, value] = words[..] else {
+            return Err(anyhow!("bad rule {:?}", s));
+        };
+        Ok(Rule {
+            kind: match kind {
+                "object" => Kind::Object,
+                "array" => Kind::Array,
+                "any" => Kind::Any,
+                _ => return Err(anyhow!("bad kind {} in {:?}", kind, s))
+            },
+            test: match test {
+                "with" => Test::With,
+                "under" => Test::Under,
+                _ => return Err(anyhow!("bad test {} in {:?}", test, s))
+            },
+            value: value.trim_matches('"').to_string(),
+            quoted: value.len() >= 2 && value.starts_with('"') && value.ends_with('"'),
+        })
+    }
+}
+
+pub fn parse_rules(s: &str) -> Result<Vec<Rule>> {
+    s.split(',').map(str::trim).filter(|r| !r.is_empty()).map(str::parse).collect()
+}
+
+impl Rule {
+    fn applies(&self, kind: Kind) -> bool {
+        self.kind == Kind::Any || self.kind == kind
+    }
+}
+
+enum Val {
+    // raw text kept around so rules can match it
+    Num(f64, String),
+    Str(String),
+    Lit(String),
+    Container(f64),
+}
+
+impl Val {
+    fn sum(&self) -> f64 {
+        match self {
+            Val::Num(n, _) => *n,
+            Val::Container(n) => *n,
+            _ => 0.0,
+        }
+    }
+
+    fn matches(&self, rule: &Rule) -> bool {
+        match self {
+            Val::Str(s) => *s == rule.value,
+            Val::Num(_, s) | Val::Lit(s) => !rule.quoted && *s == rule.value,
+            Val::Container(_) => false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Subtotal {
+    pub path: String,
+    pub sum: f64,
+    pub skipped: bool,
+}
+
+// a container that's still open
+struct Frame {
+    kind: Kind,
+    skipped: bool,
+    sum: f64,
+    // where the path went back to before this container's members
+    len: usize,
+    // how many members have come before the current one
+    i: usize,
+}
+
+impl Frame {
+    fn close(&self) -> u8 {
+        if self.kind == Kind::Object { b'}' } else { b']' }
+    }
+}
+
+// Sums numbers straight off the reader. Only the path down to the current value
+// is held, an excluded container's sum just gets dropped when it closes. Runs of
+// whitespace, string contents and numbers are taken a buffer at a time.
+pub struct Walker<'a, R: BufRead> {
+    input: R,
+    pos: usize,
+    rules: &'a [Rule],
+    depth: usize,
+    pub subtotals: Vec<Subtotal>,
+}
+
+impl<'a, R: BufRead> Walker<'a, R> {
+    // subtotals get recorded for containers down to depth, the root is depth 0
+    pub fn new(input: R, rules: &'a [Rule], depth: usize) -> Walker<'a, R> {
+        Walker {
+            input,
+            pos: 0,
+            rules,
+            depth,
+            subtotals: Vec::new(),
+        }
+    }
+
+    fn peek(&mut self) -> Result<Option<u8>> {
+        Ok(self.input.fill_buf()?.first().copied())
+    }
+
+    fn next(&mut self) -> Result<u8> {
+        match self.peek()? {
+            Some(b) => {
+                self.input.consume(1);
+                self.pos += 1;
+                Ok(b)
+            },
+            None => Err(anyhow!("unexpected end of input at byte {}", self.pos)),
+        }
+    }
+
+    // hands keep each run of bytes that pass, stopping before the first that doesn't
+    fn scan(&mut self, pass: impl Fn(u8) -> bool, mut keep: impl FnMut(&[u8])) -> Result<()> {
+        loop {
+            let buf = self.input.fill_buf()?;
+            let n = buf.iter().position(|&b| !pass(b)).unwrap_or(buf.len());
+            keep(&buf[..n]);
+            let more = n == buf.len() && n > 0;
+            self.input.consume(n);
+            self.pos += n;
+            if !more {
+                return Ok(());
+            }
+        }
+    }
+
+    fn skip_ws(&mut self) -> Result<()> {
+        self.scan(|b| matches!(b, b' ' | b'\t' | b'\r' | b'\n'), |_| ())
+    }
+
+    fn expect(&mut self, want: u8) -> Result<()> {
+        self.skip_ws()?;
+        match self.next()? {
+            b if b == want => Ok(()),
+            b => Err(anyhow!("expected {:?} but got {:?} at byte {}", want as char, b as char, self.pos - 1)),
+        }
+    }
+
+    fn string(&mut self) -> Result<String> {
+        self.expect(b'"')?;
+        let mut bytes = Vec::new();
+        loop {
+            self.scan(|b| b != b'"' && b != b'\\', |run| bytes.extend_from_slice(run))?;
+            match self.next()? {
+                b'"' => break,
+                b'\\' => match self.next()? {
+                    b'n' => bytes.push(b'\n'),
+                    b't' => bytes.push(b'\t'),
+                    b'r' => bytes.push(b'\r'),
+                    b'b' => bytes.push(8),
+                    b'f' => bytes.push(12),
+                    b'u' => {
+                        let hex = (0..4).map(|_| self.next().map(char::from)).collect::<Result<String>>()?;
+                        let c = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32).unwrap_or('\u{fffd}');
+                        bytes.extend(c.to_string().bytes());
+                    },
+                    b => bytes.push(b),
+                },
+                _ => unreachable!("scan only stops at quotes and backslashes"),
+            }
+        }
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    fn number(&mut self) -> Result<Val> {
+        let start = self.pos;
+        let mut raw = String::new();
+        self.scan(|b| matches!(b, b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E'), |run| raw.extend(run.iter().map(|&b| b as char)))?;
+        let n = raw.parse().map_err(|_| anyhow!("bad number {:?} at byte {}", raw, start))?;
+        Ok(Val::Num(n, raw))
+    }
+
+    fn literal(&mut self) -> Result<Val> {
+        let start = self.pos;
+        let mut raw = String::new();
+        self.scan(|b| b.is_ascii_lowercase(), |run| raw.extend(run.iter().map(|&b| b as char)))?;
+        match raw.as_str() {
+            "true" | "false" | "null" => Ok(Val::Lit(raw)),
+            _ => Err(anyhow!("bad literal {:?} at byte {}", raw, start)),
+        }
+    }
+
+    fn scalar(&mut self) -> Result<Val> {
+        match self.peek()? {
+            Some(b'"') => Ok(Val::Str(self.string()?)),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(b'a'..=b'z') => self.literal(),
+            Some(b) => Err(anyhow!("unexpected {:?} at byte {}", b as char, self.pos)),
+            None => Err(anyhow!("unexpected end of input at byte {}", self.pos)),
+        }
+    }
+
+    // reads up to the start of the frame's next value, returning its key in an object
+    fn member(&mut self, frame: &Frame, depth: usize, path: &mut String) -> Result<Option<String>> {
+        match frame.kind {
+            Kind::Object => {
+                let key = self.string()?;
+                self.expect(b':')?;
+                // paths are only kept as deep as subtotals get recorded
+                if depth < self.depth {
+                    path.push('.');
+                    path.push_str(&key);
+                }
+                Ok(Some(key))
+            },
+            _ => {
+                if depth < self.depth {
+                    write!(path, "[{}]", frame.i)?;
+                }
+                Ok(None)
+            },
+        }
+    }
+
+    // The containers still open are kept in a stack rather than recursing, so
+    // nesting is only limited by memory.
+    pub fn sum(&mut self) -> Result<f64> {
+        let mut path = "$".to_string();
+        let mut stack: Vec<Frame> = vec![];
+        let mut key: Option<String> = None;
+        let total = 'value: loop {
+            self.skip_ws()?;
+            let mut val = match self.peek()? {
+                Some(open @ (b'{' | b'[')) => {
+                    let kind = if open == b'{' { Kind::Object } else { Kind::Array };
+                    self.next()?;
+                    let frame = Frame {
+                        kind,
+                        skipped: self.rules.iter()
+                            .any(|r| r.applies(kind) && r.test == Test::Under && Some(&r.value) == key.as_ref()),
+                        sum: 0.0,
+                        len: path.len(),
+                        i: 0,
+                    };
+                    self.skip_ws()?;
+                    if self.peek()? == Some(frame.close()) {
+                        self.next()?;
+                        self.close(frame, stack.len(), &path)
+                    } else {
+                        key = self.member(&frame, stack.len(), &mut path)?;
+                        stack.push(frame);
+                        continue 'value;
+                    }
+                },
+                _ => self.scalar()?,
+            };
+
+            // hand finished values up to the containers they're in
+            while let Some(mut frame) = stack.pop() {
+                path.truncate(frame.len);
+                frame.sum += val.sum();
+                frame.skipped |= self.rules.iter()
+                    .any(|r| r.applies(frame.kind) && r.test == Test::With && val.matches(r));
+
+                self.skip_ws()?;
+                match self.next()? {
+                    b',' => {
+                        frame.i += 1;
+                        key = self.member(&frame, stack.len(), &mut path)?;
+                        stack.push(frame);
+                        continue 'value;
+                    },
+                    b if b == frame.close() => val = self.close(frame, stack.len(), &path),
+                    b => return Err(anyhow!("expected ',' or {:?} but got {:?} at byte {}", frame.close() as char, b as char, self.pos - 1)),
+                }
+            }
+            break val.sum();
+        };
+
+        self.skip_ws()?;
+        match self.peek()? {
+            None => Ok(total),
+            Some(b) => Err(anyhow!("trailing {:?} at byte {}", b as char, self.pos)),
+        }
+    }
+
+    fn close(&mut self, frame: Frame, depth: usize, path: &str) -> Val {
+        if depth <= self.depth {
+            self.subtotals.push(Subtotal { path: path.to_string(), sum: frame.sum, skipped: frame.skipped });
+        }
+        Val::Container(if frame.skipped { 0.0 } else { frame.sum })
+    }
+}
+
+#[cfg(test)]
+fn sum(s: &str, rules: &str) -> Result<f64> {
+    let rules = parse_rules(rules)?;
+    Walker::new(s.as_bytes(), &rules, 0).sum()
+}
+
+#[test]
+fn test_sum() {
+    assert_eq!(sum("[1,2,3]", "").unwrap(), 6.0);
+    assert_eq!(sum(r#"{"a":{"b":4},"c":-1}"#, "").unwrap(), 3.0);
+    assert_eq!(sum(r#"[[[3]]]"#, "").unwrap(), 3.0);
+    assert_eq!(sum(r#" {"a":[-1,1]} "#, "").unwrap(), 0.0);
+    assert_eq!(sum(r#"[1.5, 2e1, "3", true, null, "a\"]"]"#, "").unwrap(), 21.5);
+    assert_eq!(sum("[]", "").unwrap(), 0.0);
+    assert_eq!(sum("{}", "").unwrap(), 0.0);
+}
+
+#[test]
+fn test_rules() {
+    let red = "object with red";
+    assert_eq!(sum(r#"[1,2,3]"#, red).unwrap(), 6.0);
+    assert_eq!(sum(r#"[1,{"c":"red","b":2},3]"#, red).unwrap(), 4.0);
+    assert_eq!(sum(r#"{"d":"red","e":[1,2,3,4],"f":5}"#, red).unwrap(), 0.0);
+    assert_eq!(sum(r#"[1,"red",5]"#, red).unwrap(), 6.0);
+    assert_eq!(sum(r#"[1,"red",5]"#, "array with red").unwrap(), 0.0);
+    assert_eq!(sum(r#"{"a":{"b":"red","c":[{"x":"red"}]},"d":[1,{"e":"red"}],"f":9}"#, red).unwrap(), 10.0);
+
+    let items = r#"{"items":[1,2],"more":{"items":{"a":3}},"x":4}"#;
+    assert_eq!(sum(items, "array under items").unwrap(), 7.0);
+    assert_eq!(sum(items, "any under items").unwrap(), 4.0);
+    assert_eq!(sum(items, "array under items, object with 3").unwrap(), 4.0);
+    assert_eq!(sum(r#"[1,{"a":1},{"a":"1"}]"#, "object with \"1\"").unwrap(), 2.0);
+    assert_eq!(sum(r#"[1,{"a":1},{"a":"1"}]"#, "object with 1").unwrap(), 1.0);
+}
+
+#[test]
+fn test_errors() {
+    assert!(parse_rules("object red").is_err());
+    assert!(parse_rules("thing with red").is_err());
+    assert_eq!(sum("[1,2", "").unwrap_err().to_string(), "unexpected end of input at byte 4");
+    assert_eq!(sum("[1;2]", "").unwrap_err().to_string(), "expected ',' or ']' but got ';' at byte 2");
+    assert_eq!(sum("[1] 2", "").unwrap_err().to_string(), "trailing '2' at byte 4");
+    assert_eq!(sum("[nope]", "").unwrap_err().to_string(), "bad literal \"nope\" at byte 1");
+}
+
+#[test]
+fn test_subtotals() {
+    let rules = parse_rules("object with red").unwrap();
+    let mut walker = Walker::new(r#"{"a":[1,2],"b":{"c":"red","d":5},"e":[{"f":3}]}"#.as_bytes(), &rules, 1);
+    assert_eq!(walker.sum().unwrap(), 6.0);
+    let paths = walker.subtotals.iter().map(|s| (s.path.as_str(), s.sum, s.skipped)).collect::<Vec<_>>();
+    assert_eq!(paths, vec![("$.a", 3.0, false), ("$.b", 5.0, true), ("$.e", 3.0, false), ("$", 6.0, false)]);
+}
+
+#[test]
+fn test_small_buffer() {
+    // every run split across refills
+    let json = r#"[ 123 , "ab\"c\u0041d" , {"k": -4.5e1, "red": "red"}, true,   [10000]]"#;
+    for cap in 1..8 {
+        let rules = parse_rules("object with red").unwrap();
+        let input = std::io::BufReader::with_capacity(cap, json.as_bytes());
+        assert_eq!(Walker::new(input, &rules, 0).sum().unwrap(), 10123.0, "capacity {}", cap);
+        let input = std::io::BufReader::with_capacity(cap, json.as_bytes());
+        assert_eq!(Walker::new(input, &[], 0).sum().unwrap(), 10123.0 - 45.0, "capacity {}", cap);
+    }
+    let input = std::io::BufReader::with_capacity(2, "[1, \"abc".as_bytes());
+    assert_eq!(Walker::new(input, &[], 0).sum().unwrap_err().to_string(), "unexpected end of input at byte 8");
+}
+
+#[test]
+fn test_deep() {
+    // far deeper than the stack would go if each level recursed
+    let n = 300_000;
+    let json = format!("{}{}", "[".repeat(n), "]".repeat(n));
+    assert_eq!(sum(&json, "").unwrap(), 0.0);
+    let json = format!("{}1{}", r#"{"a":["#.repeat(n), "]}".repeat(n));
+    assert_eq!(sum(&json, "").unwrap(), 1.0);
+    assert_eq!(sum(&json, "array under a").unwrap(), 0.0);
+    let json = format!("{}1", "[".repeat(n));
+    assert_eq!(sum(&json, "").unwrap_err().to_string(), format!("unexpected end of input at byte {}", n + 1));
+}
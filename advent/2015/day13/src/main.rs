@@ -1,8 +1,10 @@
 use anyhow::{anyhow, Result};
-use itertools::Itertools;
 use lazy_static::lazy_static;
 use regex::Regex;
-use std::collections::{HashMap, HashSet};
+
+mod seating;
+
+use seating::Table;
 
 struct Rule<'a> {
     target: &'a str,
@@ -31,35 +33,19 @@ fn parse_line<'a>(l: &'a str) -> Result<Rule<'a>> {
     })
 }
 
-fn happiness<'a>(rules: &HashMap<(&'a str, &'a str), i32>, peeps: &[&'a str]) -> i32 {
-    peeps.iter().copied().cycle().tuple_windows::<(&'a str, &'a str)>().take(peeps.len()).map(|p| {
-        rules.get(&p).unwrap() + rules.get(&(p.1, p.0)).unwrap()
-    }).sum()
-}
-
 fn main() -> Result<()> {
+    let args = std::env::args().collect::<Vec<_>>();
+    let neutral = args.get(1).map_or(Ok(1), |n| n.parse())?;
     let rules = include_str!("input.txt").lines().map(parse_line).collect::<Result<Vec<_>>>()?;
-
-    let mut people = rules.iter().flat_map(|r| vec![r.target, r.other]).collect::<HashSet<_>>();
-
-    let mut combined = HashMap::new();
-    for Rule {target, amount, other} in &rules {
-        combined.insert((*target, *other), *amount);
-    }
-
-    let me = "me";
-    for person in &people {
-        combined.insert((me, person), 0);
-        combined.insert((person, me), 0);
+    let table = Table::new(&rules)?;
+
+    for neutral in [0, neutral] {
+        let best = table.best(neutral)?;
+        println!("best with {} neutral guests: {}", neutral, best.total);
+        for pair in &best.pairs {
+            println!("  {} {:+} / {:+} {} = {:+}", table.name(pair.left), pair.left_gain, pair.right_gain, table.name(pair.right), pair.total());
+        }
     }
-    people.insert(me);
-
-    let solution = people.iter().copied().permutations(people.len()).map(move |p| {
-        let happiness = happiness(&combined, &p);
-      (p, happiness)
-    }).max_by_key(|(_, h)| *h);
-
-    println!("solution: {:?}", solution);
 
     Ok(())
 }
@@ -0,0 +1,330 @@
+use std::collections::BTreeSet;
+
+use anyhow::{Result, anyhow};
+
+use crate::Rule;
+
+// the most the DP tables are allowed to take up
+const TABLE_BYTES: usize = 512 << 20;
+
+// parent flags: a neutral guest sits right before this one, and the path came up
+// from the layer with one neutral less
+const BREAK: u8 = 0x80;
+const BELOW: u8 = 0x40;
+
+pub struct Table {
+    pub names: Vec<String>,
+    // gain[a][b] is how much a likes sitting next to b
+    gain: Vec<Vec<i32>>,
+}
+
+// two neighbours going round the table, None is a neutral guest
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pair {
+    pub left: Option<usize>,
+    pub right: Option<usize>,
+    pub left_gain: i32,
+    pub right_gain: i32,
+}
+
+impl Pair {
+    pub fn total(&self) -> i32 {
+        self.left_gain + self.right_gain
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Seating {
+    pub seats: Vec<Option<usize>>,
+    pub pairs: Vec<Pair>,
+    pub total: i32,
+}
+
+impl Table {
+    pub fn new(rules: &[Rule]) -> Result<Table> {
+        let names = rules.iter().flat_map(|r| [r.target, r.other]).collect::<BTreeSet<_>>();
+        let names = names.into_iter().map(str::to_string).collect::<Vec<_>>();
+        let index = |name: &str| names.iter().position(|n| n == name).unwrap();
+        let mut gain = vec![vec![None; names.len()]; names.len()];
+        for rule in rules {
+            gain[index(rule.target)][index(rule.other)] = Some(rule.amount);
+        }
+        let gain = gain.iter().enumerate().map(|(a, row)| {
+            row.iter().enumerate().map(|(b, g)| match g {
+                _ if a == b => Ok(0),
+                Some(g) => Ok(*g),
+                None => Err(anyhow!("no rule for how {} feels about {}", names[a], names[b])),
+            }).collect()
+        }).collect::<Result<_>>()?;
+        Ok(Table { names, gain })
+    }
+
+    pub fn name(&self, seat: Option<usize>) -> &str {
+        seat.map_or("(neutral)", |g| self.names[g].as_str())
+    }
+
+    fn gain(&self, a: Option<usize>, b: Option<usize>) -> i32 {
+        match (a, b) {
+            (Some(a), Some(b)) => self.gain[a][b],
+            _ => 0,
+        }
+    }
+
+    fn pair(&self, a: usize, b: usize) -> i32 {
+        self.gain[a][b] + self.gain[b][a]
+    }
+
+    // scores seats as they go round the table
+    pub fn score(&self, seats: &[Option<usize>]) -> Seating {
+        let pairs = if seats.len() < 2 {
+            vec![]
+        } else {
+            seats.iter().zip(seats.iter().cycle().skip(1)).map(|(&left, &right)| Pair {
+                left,
+                right,
+                left_gain: self.gain(left, right),
+                right_gain: self.gain(right, left),
+            }).collect()
+        };
+        let total = pairs.iter().map(Pair::total).sum();
+        Seating { seats: seats.to_vec(), pairs, total }
+    }
+
+    // The happiest table with everyone plus some neutral guests. Guest 0 is always
+    // in the first seat so turning the table round doesn't count as new. A neutral
+    // guest just cuts the table between two people, and cutting the same spot twice
+    // does nothing new, so the DP keeps one layer per cut made. With at least as many
+    // neutrals as people they can't run out, so past the first cut one layer that
+    // cuts wherever it helps does.
+    pub fn best(&self, neutral: usize) -> Result<Seating> {
+        let n = self.names.len();
+        match table_bytes(n, neutral) {
+            Some(bytes) if bytes <= TABLE_BYTES => (),
+            bytes => return Err(anyhow!(
+                "{} guests and {} neutral need {} of tables, more than the {}MB allowed",
+                n, neutral, bytes.map_or("more than fits in memory".to_string(), |b| format!("{}MB", b >> 20)), TABLE_BYTES >> 20,
+            )),
+        }
+        if n <= 1 {
+            let seats = (0..n).map(Some).chain((0..neutral).map(|_| None)).collect::<Vec<_>>();
+            return Ok(self.score(&seats));
+        }
+
+        // dp[mask * m + last - 1] is the best path from guest 0 through the guests in
+        // mask (bit g - 1 for guest g) ending at last
+        let m = n - 1;
+        let full = (1usize << m) - 1;
+        let unlimited = neutral >= n;
+        let top = if unlimited { 1 } else { neutral };
+        let min_cuts = (neutral > 0) as usize;
+        let at = |mask: usize, last: usize| mask * m + last - 1;
+
+        let mut parents = vec![];
+        let mut below: Vec<i32> = vec![];
+        // total, layer, last guest, whether the table closes with a cut
+        let mut best: Option<(i32, usize, usize, bool)> = None;
+        for layer in 0..=top {
+            let own_cuts = unlimited && layer == top;
+            let mut dp = vec![i32::MIN; (full + 1) * m];
+            let mut parent = vec![0u8; (full + 1) * m];
+            for mask in 1..=full {
+                for last in (1..n).filter(|g| mask & 1 << (g - 1) != 0) {
+                    let rest = mask ^ 1 << (last - 1);
+                    let mut pick = (i32::MIN, 0u8);
+                    let mut choose = |value: i32, from: u8| if value > pick.0 {
+                        pick = (value, from);
+                    };
+                    if rest == 0 {
+                        match layer {
+                            0 => choose(self.pair(0, last), 0),
+                            1 => choose(0, BREAK | BELOW),
+                            _ => (),
+                        }
+                    }
+                    for prev in (1..n).filter(|g| rest & 1 << (g - 1) != 0) {
+                        let here = dp[at(rest, prev)];
+                        if here != i32::MIN {
+                            let w = self.pair(prev, last);
+                            if own_cuts && w < 0 {
+                                choose(here, prev as u8 | BREAK);
+                            } else {
+                                choose(here + w, prev as u8);
+                            }
+                        }
+                        if layer > 0 && below[at(rest, prev)] != i32::MIN {
+                            choose(below[at(rest, prev)], prev as u8 | BREAK | BELOW);
+                        }
+                    }
+                    dp[at(mask, last)] = pick.0;
+                    parent[at(mask, last)] = pick.1;
+                }
+            }
+
+            for last in 1..n {
+                let mut close = |value: i32, layer: usize, cut: bool| {
+                    let cuts = layer + cut as usize;
+                    let allowed = (min_cuts..=top).contains(&cuts) || (unlimited && cuts >= min_cuts);
+                    if allowed && best.is_none_or(|b| value > b.0) {
+                        best = Some((value, layer, last, cut));
+                    }
+                };
+                if dp[at(full, last)] != i32::MIN {
+                    close(dp[at(full, last)] + self.pair(last, 0), layer, false);
+                    if own_cuts {
+                        close(dp[at(full, last)], layer, true);
+                    }
+                }
+                if layer > 0 && below[at(full, last)] != i32::MIN {
+                    close(below[at(full, last)], layer - 1, true);
+                }
+            }
+            parents.push(parent);
+            below = dp;
+        }
+
+        let (total, mut layer, mut last, cut) = best.ok_or_else(|| anyhow!("nowhere to seat everyone"))?;
+        let mut path = vec![];
+        let mut mask = full;
+        loop {
+            let p = parents[layer][at(mask, last)];
+            path.push((last, p & BREAK != 0));
+            if p & BELOW != 0 {
+                layer -= 1;
+            }
+            mask ^= 1 << (last - 1);
+            if mask == 0 {
+                break;
+            }
+            last = (p & !(BREAK | BELOW)) as usize;
+        }
+
+        let mut seats = vec![Some(0)];
+        for (guest, cut) in path.into_iter().rev() {
+            if cut {
+                seats.push(None);
+            }
+            seats.push(Some(guest));
+        }
+        if cut {
+            seats.push(None);
+        }
+        // whoever's left over sits with the first neutral guest
+        let extra = neutral - seats.iter().filter(|s| s.is_none()).count();
+        if let Some(first) = seats.iter().position(Option::is_none) {
+            seats.splice(first..first, (0..extra).map(|_| None));
+        }
+
+        let seating = self.score(&seats);
+        debug_assert_eq!(seating.total, total);
+        Ok(seating)
+    }
+}
+
+// Every layer has an i32 for each set of free seats and who's last in it, and
+// two layers are live at once, plus a u8 parent for the same in every layer.
+// With 22 guests and no neutrals that's 2^21 * 21 * 9 bytes, about 400MB.
+fn table_bytes(guests: usize, neutral: usize) -> Option<usize> {
+    let m = guests.saturating_sub(1);
+    let states = 1usize.checked_shl(m as u32).filter(|_| m < usize::BITS as usize)?.checked_mul(m)?;
+    let layers = if neutral >= guests { 2 } else { neutral + 1 };
+    states.checked_mul(2 * 4 + layers)
+}
+
+#[cfg(test)]
+fn table(src: &str) -> Table {
+    let rules = src.lines().map(crate::parse_line).collect::<Result<Vec<_>>>().unwrap();
+    Table::new(&rules).unwrap()
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = "Alice would gain 54 happiness units by sitting next to Bob.
+Alice would lose 79 happiness units by sitting next to Carol.
+Alice would lose 2 happiness units by sitting next to David.
+Bob would gain 83 happiness units by sitting next to Alice.
+Bob would lose 7 happiness units by sitting next to Carol.
+Bob would lose 63 happiness units by sitting next to David.
+Carol would lose 62 happiness units by sitting next to Alice.
+Carol would gain 60 happiness units by sitting next to Bob.
+Carol would gain 55 happiness units by sitting next to David.
+David would gain 46 happiness units by sitting next to Alice.
+David would lose 7 happiness units by sitting next to Bob.
+David would gain 41 happiness units by sitting next to Carol.";
+
+// every arrangement with guest 0 first and the neutral guests anywhere
+#[cfg(test)]
+fn brute_force(table: &Table, neutral: usize) -> i32 {
+    use itertools::Itertools;
+    let others = (1..table.names.len()).map(Some).chain((0..neutral).map(|_| None)).collect::<Vec<_>>();
+    others.iter().copied().permutations(others.len()).map(|rest| {
+        let seats = std::iter::once(Some(0)).chain(rest).collect::<Vec<_>>();
+        table.score(&seats).total
+    }).max().unwrap()
+}
+
+#[test]
+fn test_example() {
+    let table = table(EXAMPLE);
+    let best = table.best(0).unwrap();
+    assert_eq!(best.total, 330);
+    assert_eq!(best.seats.len(), 4);
+    assert_eq!(best.seats[0], Some(0));
+    let pairs = best.pairs.iter().map(|p| (table.name(p.left), table.name(p.right), p.total())).collect::<Vec<_>>();
+    assert!(pairs.contains(&("Alice", "Bob", 137)) || pairs.contains(&("Bob", "Alice", 137)), "{:?}", pairs);
+    assert_eq!(best.pairs.iter().map(Pair::total).sum::<i32>(), 330);
+}
+
+#[test]
+fn test_neutral() {
+    let table = table(EXAMPLE);
+    for neutral in 0..=5 {
+        let best = table.best(neutral).unwrap();
+        assert_eq!(best.total, brute_force(&table, neutral), "{} neutral", neutral);
+        assert_eq!(best.seats.iter().filter(|s| s.is_none()).count(), neutral);
+        assert_eq!(best.seats.iter().filter(|s| s.is_some()).count(), 4);
+    }
+}
+
+#[test]
+fn test_against_brute_force() {
+    // a made up table with a lot of bad blood, so cutting it up actually helps
+    let names = ["A", "B", "C", "D", "E", "F", "G"];
+    let mut src = vec![];
+    for (i, a) in names.iter().enumerate() {
+        for (j, b) in names.iter().enumerate().filter(|&(j, _)| j != i) {
+            let amount = (i as i32 * 37 + j as i32 * 11) % 23 - 14;
+            let (sign, amount) = if amount < 0 { ("lose", -amount) } else { ("gain", amount) };
+            src.push(format!("{} would {} {} happiness units by sitting next to {}.", a, sign, amount, b));
+        }
+    }
+    let table = table(&src.join("\n"));
+    for neutral in 0..=3 {
+        assert_eq!(table.best(neutral).unwrap().total, brute_force(&table, neutral), "{} neutral", neutral);
+    }
+    let unlimited = table.best(7).unwrap();
+    assert!(unlimited.pairs.iter().all(|p| p.total() >= 0));
+}
+
+#[test]
+fn test_small() {
+    let table = table("A would gain 1 happiness units by sitting next to B.\nB would lose 3 happiness units by sitting next to A.");
+    assert_eq!(table.best(0).unwrap().total, -4);
+    assert_eq!(table.best(1).unwrap().total, -2);
+    assert_eq!(table.best(2).unwrap().total, 0);
+    assert!(Table::new(&[Rule { target: "A", amount: 1, other: "B" }]).is_err());
+}
+
+#[test]
+fn test_too_big() {
+    assert_eq!(table_bytes(4, 0), Some(8 * 3 * 9));
+    assert_eq!(table_bytes(4, 2), Some(8 * 3 * 11));
+    assert_eq!(table_bytes(4, 4), Some(8 * 3 * 10));
+    assert_eq!(table_bytes(100, 0), None);
+    // fine with no neutrals or as many as it likes, too big with a few
+    let n = 22;
+    let big = Table { names: (0..n).map(|i| i.to_string()).collect(), gain: vec![vec![0; n]; n] };
+    assert!(table_bytes(n, 0).unwrap() <= TABLE_BYTES);
+    assert!(table_bytes(n, n).unwrap() <= TABLE_BYTES);
+    assert!(big.best(5).is_err());
+    let big = Table { names: (0..100).map(|i| i.to_string()).collect(), gain: vec![vec![0; 100]; 100] };
+    assert!(big.best(0).unwrap_err().to_string().contains("more than fits in memory"));
+}
@@ -2,6 +2,10 @@ use anyhow::{anyhow, Result};
 use regex::Regex;
 use lazy_static::lazy_static;
 
+// how many lead changes to print
+const TIMELINE: usize = 50;
+
+#[cfg(test)]
 #[derive(Debug)]
 enum State {
     Flying,
@@ -16,6 +20,7 @@ struct ReindeerDef {
     rest_time: u32,
 }
 
+#[cfg(test)]
 #[derive(Debug)]
 struct ReindeerSim<'a> {
     def: &'a ReindeerDef,
@@ -42,7 +47,9 @@ fn parse(s: &str) -> Result<Vec<ReindeerDef>> {
     }).collect()
 }
 
-fn advance(state: &mut Vec<ReindeerSim>) {
+// the old second by second race, kept to check the event based one against
+#[cfg(test)]
+fn advance(state: &mut [ReindeerSim]) {
     for reindeer in state.iter_mut() {
         reindeer.remaining -= 1;
         match reindeer.state {
@@ -63,7 +70,8 @@ fn advance(state: &mut Vec<ReindeerSim>) {
     }
 }
 
-fn award_points(state: &mut Vec<ReindeerSim>) {
+#[cfg(test)]
+fn award_points(state: &mut [ReindeerSim]) {
     let lead_distance = state.iter().map(|r| r.flown).max().unwrap();
 
     state.iter_mut().filter(|r| r.flown == lead_distance).for_each(|leader| {
@@ -71,7 +79,8 @@ fn award_points(state: &mut Vec<ReindeerSim>) {
     });
 }
 
-fn sim(defs: &Vec<ReindeerDef>, time: u32) -> Vec<ReindeerSim> {
+#[cfg(test)]
+fn sim(defs: &[ReindeerDef], time: u32) -> Vec<ReindeerSim<'_>> {
     let mut state = defs.iter().map(|def| ReindeerSim {
         def,
         remaining:
@@ -88,12 +97,197 @@ fn sim(defs: &Vec<ReindeerDef>, time: u32) -> Vec<ReindeerSim> {
 }
 
 
+impl ReindeerDef {
+    fn cycle(&self) -> u64 {
+        (self.flight_time + self.rest_time) as u64
+    }
+
+    // whether the second after time is spent flying
+    fn flying_after(&self, time: u64) -> bool {
+        time % self.cycle() < self.flight_time as u64
+    }
+
+    fn distance(&self, time: u64) -> u64 {
+        let flown = time / self.cycle() * self.flight_time as u64 + (time % self.cycle()).min(self.flight_time as u64);
+        flown * self.speed as u64
+    }
+
+    // how much further than time / cycle * flight_time a reindeer can be off its average
+    fn wobble(&self) -> i128 {
+        self.speed as i128 * self.flight_time as i128
+    }
+
+    // when the reindeer next takes off or lands
+    fn next_change(&self, time: u64) -> u64 {
+        let at = time % self.cycle();
+        let flight = self.flight_time as u64;
+        time + if at < flight { flight - at } else { self.cycle() - at }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Lead {
+    time: u64,
+    leaders: Vec<usize>,
+}
+
+#[derive(Debug)]
+struct Race {
+    distance: Vec<u64>,
+    points: Vec<u64>,
+    // every time the set of leaders changes, from the first second on
+    timeline: Vec<Lead>,
+}
+
+// How many more seconds a leader at distance lead is sure to stay strictly ahead
+// of other at distance behind, None if it's forever. Over k seconds a reindeer
+// gets within one flight of k times its average speed, so this only needs the
+// gap and the averages, not every take off and landing on the way.
+fn safe_lead(leader: &ReindeerDef, lead: u64, other: &ReindeerDef, behind: u64) -> Option<u64> {
+    let (cl, co) = (leader.cycle() as i128, other.cycle() as i128);
+    let margin = (lead as i128 - behind as i128 - leader.wobble() - other.wobble()) * cl * co;
+    if margin <= 0 {
+        return Some(0);
+    }
+    // how much faster other gains on average, times both cycles
+    let gaining = other.wobble() * cl - leader.wobble() * co;
+    if gaining <= 0 {
+        return None;
+    }
+    Some(((margin - 1) / gaining) as u64)
+}
+
+// Between two take offs or landings everybody's distance goes up in a straight
+// line, so the lead only changes where the lines cross. Each step finds the
+// leaders at some second, works out how long they stay the leaders, and hands
+// out all of those points in one go. A leader that's far enough ahead can't be
+// caught for a while no matter who's flying, so that skips a lot of changes too.
+fn race(defs: &[ReindeerDef], time: u64) -> Race {
+    let mut points = vec![0; defs.len()];
+    let mut timeline: Vec<Lead> = vec![];
+    let mut t = 1;
+    while t <= time && !defs.is_empty() {
+        let distance = defs.iter().map(|d| d.distance(t)).collect::<Vec<_>>();
+        let speed = defs.iter().map(|d| if d.flying_after(t) { d.speed as u64 } else { 0 }).collect::<Vec<_>>();
+        let lead = *distance.iter().max().unwrap();
+        let leaders = (0..defs.len()).filter(|&i| distance[i] == lead).collect::<Vec<_>>();
+        let top = leaders.iter().map(|&i| speed[i]).max().unwrap();
+
+        // the straight lines hold up to and including the next change
+        let lines = defs.iter().map(|d| d.next_change(t)).min().unwrap() - t;
+        let stays = if leaders.iter().any(|&i| speed[i] < top) {
+            // a tie that's about to break up
+            1
+        } else {
+            (0..defs.len()).filter(|&j| speed[j] > top).map(|j| {
+                let (gap, closing) = (lead - distance[j], speed[j] - top);
+                gap.div_ceil(closing)
+            }).min().unwrap_or(u64::MAX)
+        };
+        let mut span = stays.min(lines + 1);
+        if let [leader] = leaders[..] {
+            let safe = (0..defs.len()).filter(|&j| j != leader)
+                .filter_map(|j| safe_lead(&defs[leader], lead, &defs[j], distance[j]))
+                .min();
+            span = span.max(safe.map_or(u64::MAX, |k| k.saturating_add(1)));
+        }
+        let span = span.min(time - t + 1);
+
+        for &i in &leaders {
+            points[i] += span;
+        }
+        if timeline.last().map(|l| &l.leaders) != Some(&leaders) {
+            timeline.push(Lead { time: t, leaders });
+        }
+        t += span;
+    }
+    Race {
+        distance: defs.iter().map(|d| d.distance(time)).collect(),
+        points,
+        timeline,
+    }
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = "Comet can fly 14 km/s for 10 seconds, but then must rest for 127 seconds.
+Dancer can fly 16 km/s for 11 seconds, but then must rest for 162 seconds.";
+
+#[test]
+fn test_distance() {
+    let defs = parse(EXAMPLE).unwrap();
+    assert_eq!(defs[0].distance(1000), 1120);
+    assert_eq!(defs[1].distance(1000), 1056);
+    let defs = parse(include_str!("input.txt")).unwrap();
+    let state = sim(&defs, 1000);
+    for (def, r) in defs.iter().zip(&state) {
+        assert_eq!(def.distance(1000), r.flown as u64, "{}", def.name);
+    }
+}
+
+#[test]
+fn test_example() {
+    let defs = parse(EXAMPLE).unwrap();
+    let race = race(&defs, 1000);
+    assert_eq!(race.points, vec![312, 689]);
+    assert_eq!(race.distance, vec![1120, 1056]);
+    assert_eq!(race.timeline[0], Lead { time: 1, leaders: vec![1] });
+}
+
+#[test]
+fn test_against_sim() {
+    let mut src = include_str!("input.txt").to_string();
+    // a couple that tie and keep swapping places with the others
+    src.push_str("Twin can fly 22 km/s for 8 seconds, but then must rest for 165 seconds.\n");
+    src.push_str("Slow can fly 2 km/s for 100 seconds, but then must rest for 1 seconds.\n");
+    let defs = parse(&src).unwrap();
+    for time in [1, 2, 10, 137, 1000, 2503, 60000] {
+        let mut state = defs.iter().map(|def| ReindeerSim { def, remaining: def.flight_time, flown: 0, points: 0, state: State::Flying }).collect::<Vec<_>>();
+        let mut timeline: Vec<Lead> = vec![];
+        for t in 1..=time {
+            advance(&mut state);
+            let lead = state.iter().map(|r| r.flown).max().unwrap();
+            let leaders = (0..state.len()).filter(|&i| state[i].flown == lead).collect::<Vec<_>>();
+            if timeline.last().map(|l| &l.leaders) != Some(&leaders) {
+                timeline.push(Lead { time: t as u64, leaders });
+            }
+            award_points(&mut state);
+        }
+        let race = race(&defs, time as u64);
+        assert_eq!(race.points, state.iter().map(|r| r.points as u64).collect::<Vec<_>>(), "{}", time);
+        assert_eq!(race.timeline, timeline, "{}", time);
+    }
+    assert_eq!(sim(&defs, 2503).iter().map(|r| r.points as u64).collect::<Vec<_>>(), race(&defs, 2503).points);
+}
+
+#[test]
+fn test_long_race() {
+    let defs = parse(include_str!("input.txt")).unwrap();
+    let race = race(&defs, 3_000_000_000);
+    // Vixen has the best average and pulls away for good after a while
+    let vixen = defs.iter().position(|d| d.name == "Vixen").unwrap();
+    assert_eq!(race.timeline.last().unwrap().leaders, vec![vixen]);
+    assert!(race.points[vixen] > 2_999_000_000);
+    assert_eq!(race.distance[vixen], defs[vixen].distance(3_000_000_000));
+}
+
 fn main() -> Result<()> {
+    let time = std::env::args().nth(1).map_or(Ok(2503), |t| t.parse())?;
     let defs = parse(include_str!("input.txt"))?;
-    let res = sim(&defs, 2503);
-    let winner = res.iter().max_by_key(|reindeer| reindeer.points).unwrap();
+    let race = race(&defs, time);
+
+    let far = (0..defs.len()).max_by_key(|&i| race.distance[i]).ok_or(anyhow!("no reindeer"))?;
+    println!("Furthest is {} at {} km", defs[far].name, race.distance[far]);
+    let winner = (0..defs.len()).max_by_key(|&i| race.points[i]).ok_or(anyhow!("no reindeer"))?;
+    println!("Winner is {} at {} points", defs[winner].name, race.points[winner]);
 
-    println!("Winner is {} at {} points", winner.def.name, winner.points);
+    println!("{} lead changes", race.timeline.len());
+    for lead in race.timeline.iter().take(TIMELINE) {
+        let names = lead.leaders.iter().map(|&i| defs[i].name.as_str()).collect::<Vec<_>>();
+        println!("  {:>6}s {}", lead.time, names.join(", "));
+    }
+    if race.timeline.len() > TIMELINE {
+        println!("  ... and {} more", race.timeline.len() - TIMELINE);
+    }
 
     Ok(())
 }
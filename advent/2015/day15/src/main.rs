@@ -1,130 +1,78 @@
-use std::num::ParseIntError;
-
 use anyhow::{anyhow, Result};
 use lazy_regex::regex;
 
-#[derive(Clone, Debug)]
-struct Ingredient {
-    name: String,
-    capacity: i32,
-    durability: i32,
-    flavor: i32,
-    texture: i32,
-    calories: i32,
-}
+mod recipe;
 
-fn parse_line(s: &str) -> Result<Ingredient> {
-    let name = s.split(":").next().ok_or(anyhow!("no name"))?;
-    let params = regex!(r"-?\d+").find_iter(s).map(|m| m.as_str().parse::<i32>()).collect::<Result<Vec<_>, ParseIntError>>()?;
+use recipe::Recipe;
 
-    Ok(Ingredient {
-        name: name.to_string(),
-        capacity: params[0],
-        durability: params[1],
-        flavor: params[2],
-        texture: params[3],
-        calories: params[4],
-    })
+#[derive(Clone, Debug)]
+pub struct Ingredient {
+    pub name: String,
+    // in the same order as the property names that come out of parse
+    pub props: Vec<i64>,
 }
 
-fn combinations<'a, F>(ingredients : &'a [Ingredient], mut cb: F)
-    where F: FnMut(Vec<(&'a Ingredient, i32)>)
-{
-    fn inner<'a, F>(ingredients: &'a [Ingredient], picks: Vec<(&'a Ingredient,i32)>, cb: &mut F)
-        where F: FnMut(Vec<(&'a Ingredient, i32)>)
-    {
-        let so_far: i32 = picks.iter().map(|i| i.1).sum();
-        if ingredients.is_empty() {
-            if so_far == 100 {
-                cb(picks);
-            }
-            return
-        }
-
-        let remaining = 100 - so_far;
-
-        for i in (0..=remaining).rev() {
-            let last_pos = ingredients.len() -1;
-            let next_ingredient = &ingredients[last_pos];
+fn parse_line(s: &str) -> Result<(Ingredient, Vec<String>)> {
+    let (name, rest) = s.split_once(':').ok_or(anyhow!("no name"))?;
+    let (names, props) = regex!(r"(\w+) (-?\d+)").captures_iter(rest).map(|caps| {
+        Ok((caps[1].to_string(), caps[2].parse::<i64>()?))
+    }).collect::<Result<Vec<_>>>()?.into_iter().unzip();
 
-            let mut picks = picks.clone();
-            picks.push((next_ingredient, i));
-
-            inner(&ingredients[..(last_pos)], picks, cb);
-        }
-    }
-
-    inner(ingredients, vec![], &mut cb)
+    Ok((Ingredient { name: name.to_string(), props }, names))
 }
 
-fn clamp_to_zero(n: &mut i32) {
-    if *n < 0 {
-        *n = 0;
-    }
-}
-
-fn score(picks: &[(&Ingredient, i32)]) -> i32 {
-    let mut totals = Ingredient {
-        name: "Recipe".to_string(),
-        capacity: 0,
-        durability: 0,
-        flavor: 0,
-        texture: 0,
-        calories: 0,
-    };
-
-    for (ingredient, amount) in picks {
-        totals.capacity += ingredient.capacity * amount;
-        totals.durability += ingredient.durability * amount;
-        totals.flavor += ingredient.flavor * amount;
-        totals.texture += ingredient.texture * amount;
+// the property names and the ingredients, which all have to have the same properties
+fn parse(s: &str) -> Result<(Vec<String>, Vec<Ingredient>)> {
+    let mut properties = None;
+    let mut ingredients = vec![];
+    for line in s.lines() {
+        let (ingredient, names) = parse_line(line)?;
+        match &properties {
+            None => properties = Some(names),
+            Some(p) if *p != names => return Err(anyhow!("{} has properties {:?}, not {:?}", ingredient.name, names, p)),
+            _ => (),
+        }
+        ingredients.push(ingredient);
     }
-
-    clamp_to_zero(&mut totals.capacity);
-    clamp_to_zero(&mut totals.durability);
-    clamp_to_zero(&mut totals.flavor);
-    clamp_to_zero(&mut totals.texture);
-
-    totals.capacity * totals.durability * totals.flavor * totals.texture
-}
-
-fn calories(picks: &[(&Ingredient, i32)]) -> i32 {
-    picks.iter().map(|(i, a)| i.calories * a).sum()
+    Ok((properties.unwrap_or_default(), ingredients))
 }
 
 #[test]
 fn test_score() {
-    let ingredients = "Butterscotch: capacity -1, durability -2, flavor 6, texture 3, calories 8
-Cinnamon: capacity 2, durability 3, flavor -2, texture -1, calories 3".lines().map(parse_line).collect::<Result<Vec<Ingredient>>>().unwrap();
+    let (properties, ingredients) = parse("Butterscotch: capacity -1, durability -2, flavor 6, texture 3, calories 8
+Cinnamon: capacity 2, durability 3, flavor -2, texture -1, calories 3").unwrap();
+    assert_eq!(properties, vec!["capacity", "durability", "flavor", "texture", "calories"]);
 
-    let combination = vec![(&ingredients[0], 44), (&ingredients[1], 56)];
-
-    assert_eq!(score(&combination), 62842880);
+    let recipe = Recipe::new(ingredients, properties, 100, &["calories"]);
+    assert_eq!(recipe.score(&[44, 56]), 62842880);
+    assert!(parse("A: x 1, y 2\nB: x 1, z 2").is_err());
 }
 
-fn main() -> Result<()>{
-    let ingredients = include_str!("input.txt").lines().map(parse_line).collect::<Result<Vec<Ingredient>>>()?;
-
-    let mut best: Option<(Vec<(&Ingredient, i32)>, i32)> = None;
-
-    combinations(&ingredients, |ins| {
-        if calories(&ins) != 500 {
-            return
-        }
-        let score = score(&ins);
+fn main() -> Result<()> {
+    let args = std::env::args().collect::<Vec<_>>();
+    let total = args.get(1).map_or(Ok(100), |t| t.parse())?;
+    let constraints = args.get(2).map_or("calories=500", |c| c.as_str());
+    let unscored = args.get(3).map_or("calories", |u| u.as_str()).split(',').collect::<Vec<_>>();
+    let input = match args.get(4) {
+        Some(path) => std::fs::read_to_string(path)?,
+        None => include_str!("input.txt").to_string(),
+    };
 
-        let better = match best {
-            None => true,
-            Some((_, prev)) if prev < score => true,
-            _ => false
-        };
+    let (properties, ingredients) = parse(&input)?;
+    let mut recipe = Recipe::new(ingredients, properties, total, &unscored);
+    recipe.constrain(constraints)?;
 
-        if better {
-             best = Some((ins, score));
-        }
-    });
-
-    dbg!(best.unwrap());
+    match recipe.solve() {
+        Some(best) => {
+            for (ingredient, amount) in recipe.ingredients.iter().zip(&best.amounts) {
+                println!("{:>4} {}", amount, ingredient.name);
+            }
+            let totals = recipe.properties.iter().zip(&best.totals).map(|(p, t)| format!("{} {}", p, t)).collect::<Vec<_>>();
+            println!("{}", totals.join(", "));
+            println!("score {}", best.score);
+        },
+        None => println!("no recipe meets {:?}", constraints),
+    }
 
     Ok(())
 }
@@ -0,0 +1,380 @@
+use anyhow::{Result, anyhow};
+
+use crate::Ingredient;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cmp {
+    Eq,
+    Le,
+    Ge,
+}
+
+impl Cmp {
+    fn holds(self, have: i64, want: i64) -> bool {
+        match self {
+            Cmp::Eq => have == want,
+            Cmp::Le => have <= want,
+            Cmp::Ge => have >= want,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Target {
+    // the recipe's total for a property
+    Property(usize),
+    // how much of one ingredient goes in
+    Ingredient(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Constraint {
+    pub target: Target,
+    pub cmp: Cmp,
+    pub value: i64,
+}
+
+pub struct Recipe {
+    pub ingredients: Vec<Ingredient>,
+    pub properties: Vec<String>,
+    pub total: i64,
+    // the properties multiplied together for the score
+    pub scored: Vec<usize>,
+    pub constraints: Vec<Constraint>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Solution {
+    pub amounts: Vec<i64>,
+    pub totals: Vec<i64>,
+    pub score: i64,
+}
+
+impl Recipe {
+    // Everything but the properties named in unscored counts towards the score,
+    // and no constraints yet.
+    pub fn new(ingredients: Vec<Ingredient>, properties: Vec<String>, total: i64, unscored: &[&str]) -> Recipe {
+        let scored = (0..properties.len()).filter(|&p| !unscored.contains(&properties[p].as_str())).collect();
+        Recipe { ingredients, properties, total, scored, constraints: vec![] }
+    }
+
+    // comma separated, each like "calories=500", "texture>=10" or "Sugar<=30"
+    pub fn constrain(&mut self, s: &str) -> Result<()> {
+        for c in s.split(',').map(str::trim).filter(|c| !c.is_empty()) {
+            let (at, cmp, len) = if let Some(at) = c.find("<=") {
+                (at, Cmp::Le, 2)
+            } else if let Some(at) = c.find(">=") {
+                (at, Cmp::Ge, 2)
+            } else if let Some(at) = c.find('=') {
+                (at, Cmp::Eq, 1)
+            } else {
+                return Err(anyhow!("no =, <= or >= in {:?}", c));
+            };
+            let name = c[..at].trim();
+            let target = if let Some(p) = self.properties.iter().position(|p| p == name) {
+                Target::Property(p)
+            } else if let Some(i) = self.ingredients.iter().position(|i| i.name == name) {
+                Target::Ingredient(i)
+            } else {
+                return Err(anyhow!("no property or ingredient called {:?}", name));
+            };
+            let value = c[at + len..].trim().parse().map_err(|_| anyhow!("bad number in {:?}", c))?;
+            self.constraints.push(Constraint { target, cmp, value });
+        }
+        Ok(())
+    }
+
+    pub fn totals(&self, amounts: &[i64]) -> Vec<i64> {
+        (0..self.properties.len()).map(|p| {
+            self.ingredients.iter().zip(amounts).map(|(i, a)| i.props[p] * a).sum()
+        }).collect()
+    }
+
+    fn score_totals(&self, totals: &[i64]) -> i64 {
+        self.scored.iter().fold(1i64, |score, &p| score.saturating_mul(totals[p].max(0)))
+    }
+
+    #[cfg(test)]
+    pub fn score(&self, amounts: &[i64]) -> i64 {
+        self.score_totals(&self.totals(amounts))
+    }
+
+    pub fn allowed(&self, amounts: &[i64]) -> bool {
+        let totals = self.totals(amounts);
+        amounts.iter().sum::<i64>() == self.total && self.constraints.iter().all(|c| match c.target {
+            Target::Property(p) => c.cmp.holds(totals[p], c.value),
+            Target::Ingredient(i) => c.cmp.holds(amounts[i], c.value),
+        })
+    }
+
+    // The best recipe that meets every constraint, if any does. Ingredients are
+    // picked one at a time, and a partial recipe gets dropped as soon as the units
+    // left can't meet the constraints or can't beat the best score so far.
+    pub fn solve(&self) -> Option<Solution> {
+        let n = self.ingredients.len();
+        let mut lo = vec![0; n];
+        let mut hi = vec![self.total; n];
+        for c in &self.constraints {
+            if let Target::Ingredient(i) = c.target {
+                match c.cmp {
+                    Cmp::Eq => {
+                        lo[i] = lo[i].max(c.value);
+                        hi[i] = hi[i].min(c.value);
+                    },
+                    Cmp::Le => hi[i] = hi[i].min(c.value),
+                    Cmp::Ge => lo[i] = lo[i].max(c.value),
+                }
+            }
+        }
+        if n == 0 || self.total < 0 || (0..n).any(|i| lo[i] > hi[i]) {
+            return None;
+        }
+
+        let mut search = Search {
+            recipe: self,
+            lo,
+            hi,
+            amounts: vec![0; n],
+            totals: vec![0; self.properties.len()],
+            // an exact total narrows things down the most, so that goes first
+            budget: self.constraints.iter().filter_map(|c| match c.target {
+                Target::Property(p) => Some((p, c.cmp, c.value)),
+                _ => None,
+            }).min_by_key(|&(_, cmp, _)| cmp != Cmp::Eq),
+            best: None,
+        };
+        if search.possible(0, self.total) && search.promising(search.bound(0, self.total)) {
+            search.go(0, self.total);
+        }
+        search.best
+    }
+}
+
+struct Search<'a> {
+    recipe: &'a Recipe,
+    lo: Vec<i64>,
+    hi: Vec<i64>,
+    amounts: Vec<i64>,
+    totals: Vec<i64>,
+    // the property constraint the bounds take into account
+    budget: Option<(usize, Cmp, i64)>,
+    best: Option<Solution>,
+}
+
+impl Search<'_> {
+    fn ingredients(&self) -> &[Ingredient] {
+        &self.recipe.ingredients
+    }
+
+    // biggest and smallest amount of property p that units spread over ingredients
+    // from on can add
+    fn range(&self, from: usize, p: usize, units: i64) -> (i64, i64) {
+        let coefs = self.ingredients()[from..].iter().map(|i| i.props[p]);
+        let (min, max) = coefs.fold((i64::MAX, i64::MIN), |(lo, hi), c| (lo.min(c), hi.max(c)));
+        (min * units, max * units)
+    }
+
+    fn possible(&self, from: usize, units: i64) -> bool {
+        let n = self.ingredients().len();
+        if units < self.lo[from..].iter().sum() || units > self.hi[from..n].iter().sum() {
+            return false;
+        }
+        self.recipe.constraints.iter().all(|c| match c.target {
+            Target::Property(p) => {
+                let (min, max) = self.range(from, p, units);
+                let (min, max) = (self.totals[p] + min, self.totals[p] + max);
+                match c.cmp {
+                    Cmp::Eq => min <= c.value && c.value <= max,
+                    Cmp::Le => min <= c.value,
+                    Cmp::Ge => max >= c.value,
+                }
+            },
+            Target::Ingredient(_) => true,
+        })
+    }
+
+    // The most units spread over ingredients from on can add to value. The first
+    // constraint on a property total gets taken into account too, and with just the
+    // one the best mix is either a single ingredient that keeps to it or two that
+    // hit it exactly. None if it can't be kept to at all.
+    fn most(&self, from: usize, units: i64, value: impl Fn(&Ingredient) -> f64) -> Option<f64> {
+        let ingredients = &self.ingredients()[from..];
+        let Some((p, cmp, want)) = self.budget else {
+            return ingredients.iter().map(value).reduce(f64::max).map(|v| v * units as f64);
+        };
+        let need = want - self.totals[p];
+        if units == 0 {
+            return cmp.holds(0, need).then_some(0.0);
+        }
+        let mut most = None;
+        for a in ingredients {
+            let lo = a.props[p] * units;
+            if cmp.holds(lo, need) {
+                most = Some(most.map_or(value(a), |m: f64| m.max(value(a))));
+            }
+            for b in ingredients {
+                let hi = b.props[p] * units;
+                if lo < need && need < hi {
+                    let mix = (need - lo) as f64 / (hi - lo) as f64;
+                    let v = value(a) + mix * (value(b) - value(a));
+                    most = Some(most.map_or(v, |m: f64| m.max(v)));
+                }
+            }
+        }
+        most.map(|v| v * units as f64)
+    }
+
+    // Most the score could get to with units left for ingredients from on. Each
+    // property on its own can't do better than the best mix for it. Once there's a
+    // best recipe, weighting each property by one over its total there and using
+    // AM-GM gives a much tighter bound near that recipe.
+    fn bound(&self, from: usize, units: i64) -> f64 {
+        let recipe = self.recipe;
+        let mut alone = 1.0;
+        for &p in &recipe.scored {
+            let Some(most) = self.most(from, units, |i| i.props[p] as f64) else {
+                return f64::NEG_INFINITY;
+            };
+            alone *= (self.totals[p] as f64 + most).max(0.0);
+        }
+        let Some(best) = self.best.as_ref().filter(|b| b.score > 0) else {
+            return alone;
+        };
+        let weights = recipe.scored.iter().map(|&p| 1.0 / best.totals[p] as f64).collect::<Vec<_>>();
+        let weighted = |i: &Ingredient| recipe.scored.iter().zip(&weights).map(|(&p, w)| w * i.props[p] as f64).sum::<f64>();
+        let here = recipe.scored.iter().zip(&weights).map(|(&p, w)| w * self.totals[p] as f64).sum::<f64>();
+        let sum = here + self.most(from, units, weighted).unwrap_or(f64::NEG_INFINITY);
+        if sum <= 0.0 {
+            return 0.0;
+        }
+        let k = recipe.scored.len() as f64;
+        let weighted = (sum / k).powf(k) * recipe.scored.iter().map(|&p| best.totals[p] as f64).product::<f64>();
+        alone.min(weighted)
+    }
+
+    // scores are whole numbers, so a bound has to reach the next one up
+    fn promising(&self, bound: f64) -> bool {
+        let best = self.best.as_ref().map_or(-1, |b| b.score);
+        bound * (1.0 + 1e-9) >= (best + 1) as f64
+    }
+
+    fn add(&mut self, i: usize, amount: i64) {
+        self.amounts[i] += amount;
+        for (t, c) in self.totals.iter_mut().zip(&self.recipe.ingredients[i].props) {
+            *t += c * amount;
+        }
+    }
+
+    // Tries the amounts of ingredient i with the best bounds first, so a good recipe
+    // turns up early, and stops at the first one that can't beat it.
+    fn go(&mut self, i: usize, units: i64) {
+        if i == self.ingredients().len() - 1 {
+            self.add(i, units);
+            if self.recipe.allowed(&self.amounts) {
+                let score = self.recipe.score_totals(&self.totals);
+                if self.best.as_ref().is_none_or(|b| score > b.score) {
+                    self.best = Some(Solution { amounts: self.amounts.clone(), totals: self.totals.clone(), score });
+                }
+            }
+            self.add(i, -units);
+            return;
+        }
+        let mut next = vec![];
+        for amount in self.lo[i]..=self.hi[i].min(units) {
+            self.add(i, amount);
+            if self.possible(i + 1, units - amount) {
+                next.push((self.bound(i + 1, units - amount), amount));
+            }
+            self.add(i, -amount);
+        }
+        next.sort_by(|a, b| b.0.total_cmp(&a.0));
+        for (bound, amount) in next {
+            if !self.promising(bound) {
+                break;
+            }
+            self.add(i, amount);
+            self.go(i + 1, units - amount);
+            self.add(i, -amount);
+        }
+    }
+}
+
+#[cfg(test)]
+fn recipe(src: &str, total: i64, constraints: &str) -> Recipe {
+    let (properties, ingredients) = crate::parse(src).unwrap();
+    let mut recipe = Recipe::new(ingredients, properties, total, &["calories"]);
+    recipe.constrain(constraints).unwrap();
+    recipe
+}
+
+// every way to split total up, the old fashioned way
+#[cfg(test)]
+fn brute_force(recipe: &Recipe) -> Option<i64> {
+    fn go(recipe: &Recipe, amounts: &mut Vec<i64>, left: i64, best: &mut Option<i64>) {
+        if amounts.len() == recipe.ingredients.len() - 1 {
+            amounts.push(left);
+            if recipe.allowed(amounts) {
+                *best = (*best).max(Some(recipe.score(amounts)));
+            }
+            amounts.pop();
+            return;
+        }
+        for a in 0..=left {
+            amounts.push(a);
+            go(recipe, amounts, left - a, best);
+            amounts.pop();
+        }
+    }
+    let mut best = None;
+    go(recipe, &mut vec![], recipe.total, &mut best);
+    best
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = "Butterscotch: capacity -1, durability -2, flavor 6, texture 3, calories 8
+Cinnamon: capacity 2, durability 3, flavor -2, texture -1, calories 3";
+
+#[test]
+fn test_example() {
+    let best = recipe(EXAMPLE, 100, "").solve().unwrap();
+    assert_eq!((best.amounts, best.score), (vec![44, 56], 62842880));
+    let best = recipe(EXAMPLE, 100, "calories=500").solve().unwrap();
+    assert_eq!((best.amounts, best.score), (vec![40, 60], 57600000));
+    assert_eq!(recipe(EXAMPLE, 100, "calories=501").solve(), None);
+}
+
+#[test]
+fn test_constraints() {
+    let mut r = recipe(EXAMPLE, 100, "");
+    assert!(r.constrain("calories<500").is_err());
+    assert!(r.constrain("sprinkles=3").is_err());
+    assert!(r.constrain("calories=lots").is_err());
+
+    let best = recipe(EXAMPLE, 100, "Butterscotch<=30").solve().unwrap();
+    assert_eq!(best.amounts[0], 30);
+    let best = recipe(EXAMPLE, 100, "flavor>=300, Cinnamon>=10").solve().unwrap();
+    assert!(best.totals[2] >= 300 && best.amounts[1] >= 10);
+}
+
+#[test]
+fn test_against_brute_force() {
+    let src = include_str!("input.txt");
+    for (total, constraints) in [(30, ""), (30, "calories=100"), (25, "calories<=60"), (20, "texture>=5, Sugar<=6"), (12, "calories=7")] {
+        let r = recipe(src, total, constraints);
+        assert_eq!(r.solve().map(|s| s.score), brute_force(&r), "{} {:?}", total, constraints);
+    }
+    for constraints in ["calories=70", "calories<=60", "Frosting>=2"] {
+        let r = recipe(include_str!("variant.txt"), 14, constraints);
+        assert_eq!(r.solve().map(|s| s.score), brute_force(&r), "{:?}", constraints);
+    }
+}
+
+#[test]
+fn test_variant() {
+    // eight ingredients and 200 units, far too many to go through one by one
+    let r = recipe(include_str!("variant.txt"), 200, "calories=1000");
+    let best = r.solve().unwrap();
+    assert!(r.allowed(&best.amounts));
+    assert_eq!(r.score(&best.amounts), best.score);
+    assert_eq!(best.score, 925621320);
+}
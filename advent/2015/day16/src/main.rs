@@ -1,7 +1,10 @@
 use anyhow::{anyhow, Result};
 use lazy_regex::regex;
 use std::collections::HashMap;
-use std::iter::FromIterator;
+
+mod query;
+
+use query::Query;
 
 fn parse_line(s: &str) -> Result<HashMap<String, i32>> {
     let mut props = HashMap::new();
@@ -16,38 +19,25 @@ fn parse_line(s: &str) -> Result<HashMap<String, i32>> {
     Ok(props)
 }
 
-fn prop_check(target: &HashMap<String, i32>, prop: &str, amt: i32) -> bool {
-    let val = target[prop];
-    match prop {
-        "cats" | "trees" => val < amt,
-        "pomeranians" | "goldfish" => val > amt,
-        _ => val == amt
-    }
-}
+// what the MFCSAM read off the gift, with the ranges from the second half
+const TICKER: &str = "children=3, cats>7, samoyeds=2, pomeranians<3, akitas=0, vizslas=0, goldfish<5, trees>3, cars=2, perfumes=1";
+// how many aunts to show
+const SHOW: usize = 5;
 
 fn main() -> Result<()> {
+    let query = std::env::args().nth(1).unwrap_or_else(|| TICKER.to_string()).parse::<Query>()?;
     let aunts = include_str!("input.txt").lines().map(parse_line).collect::<Result<Vec<HashMap<String, i32>>>>()?;
-    let target: HashMap<String, i32> = HashMap::from_iter([
-        ("children".to_string(), 3),
-        ("cats".to_string(), 7),
-        ("samoyeds".to_string(), 2),
-        ("pomeranians".to_string(), 3),
-        ("akitas".to_string(), 0),
-        ("vizslas".to_string(), 0),
-        ("goldfish".to_string(), 5),
-        ("trees".to_string(), 3),
-        ("cars".to_string(), 2),
-        ("perfumes".to_string(), 1),
-    ]);
-
-    let sol = aunts.iter().enumerate().filter(|(_, aunt)| {
-        aunt.iter().all(|(prop, &amt)| {
-            prop_check(&target, prop, amt)
-        })
-    });
-
-    for (n, aunt) in sol {
-        println!("Aunt {}: {:?}", n + 1, aunt);
+
+    for m in query.rank(&aunts).iter().take(SHOW) {
+        let matched = m.matched.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+        let failed = m.failed.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+        println!("Aunt {}: {} of {} match{} [{}]{}",
+            m.aunt + 1,
+            m.matched.len(),
+            query.0.len(),
+            if m.exact() { "" } else { ", not a match" },
+            matched.join(", "),
+            if failed.is_empty() { String::new() } else { format!(" but not [{}]", failed.join(", ")) });
     }
 
     Ok(())
@@ -0,0 +1,155 @@
+use std::{cmp::Reverse, collections::HashMap, fmt, str::FromStr};
+
+use anyhow::{anyhow, Result};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cmp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+// the first of these is how each one gets printed
+const CMPS: [(&str, Cmp); 7] = [
+    ("=", Cmp::Eq),
+    ("!=", Cmp::Ne),
+    ("<", Cmp::Lt),
+    ("<=", Cmp::Le),
+    (">", Cmp::Gt),
+    (">=", Cmp::Ge),
+    ("==", Cmp::Eq),
+];
+
+impl fmt::Display for Cmp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = CMPS.iter().find(|(_, c)| c == self).unwrap().0;
+        write!(f, "{}", s)
+    }
+}
+
+// what the aunt's amount has to be, like cats>7
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Predicate {
+    pub prop: String,
+    pub cmp: Cmp,
+    pub value: i32,
+}
+
+impl Predicate {
+    pub fn test(&self, amount: i32) -> bool {
+        match self.cmp {
+            Cmp::Eq => amount == self.value,
+            Cmp::Ne => amount != self.value,
+            Cmp::Lt => amount < self.value,
+            Cmp::Le => amount <= self.value,
+            Cmp::Gt => amount > self.value,
+            Cmp::Ge => amount >= self.value,
+        }
+    }
+}
+
+impl FromStr for Predicate {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        // the operator that starts first, and the longest one there so "<=" isn't read as "<"
+        let (at, op, cmp) = CMPS.iter()
+            .filter_map(|&(op, cmp)| s.find(op).map(|at| (at, op, cmp)))
+            .min_by_key(|&(at, op, _)| (at, Reverse(op.len())))
+            .ok_or_else(|| anyhow!("no comparison in {:?}", s))?;
+        let prop = s[..at].trim();
+        if prop.is_empty() || !prop.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            return Err(anyhow!("bad property {:?} in {:?}", prop, s));
+        }
+        let value = s[at + op.len()..].trim().parse().map_err(|_| anyhow!("bad amount in {:?}", s))?;
+        Ok(Predicate { prop: prop.to_string(), cmp, value })
+    }
+}
+
+impl fmt::Display for Predicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}{}", self.prop, self.cmp, self.value)
+    }
+}
+
+// comma separated predicates, e.g. "cats>7, trees>3, pomeranians<3, akitas=0"
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Query(pub Vec<Predicate>);
+
+impl FromStr for Query {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let preds = s.split(',').map(str::trim).filter(|p| !p.is_empty()).map(str::parse).collect::<Result<Vec<_>>>()?;
+        Ok(Query(preds))
+    }
+}
+
+// how one aunt did against a query; props she doesn't have a record of don't count either way
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Match<'a> {
+    pub aunt: usize,
+    pub matched: Vec<&'a Predicate>,
+    pub failed: Vec<&'a Predicate>,
+}
+
+impl Match<'_> {
+    pub fn exact(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+impl Query {
+    pub fn check<'a>(&'a self, aunt: usize, props: &HashMap<String, i32>) -> Match<'a> {
+        let (matched, failed) = self.0.iter()
+            .filter_map(|p| props.get(&p.prop).map(|&amount| (p, p.test(amount))))
+            .partition::<Vec<_>, _>(|&(_, ok)| ok);
+        Match {
+            aunt,
+            matched: matched.into_iter().map(|(p, _)| p).collect(),
+            failed: failed.into_iter().map(|(p, _)| p).collect(),
+        }
+    }
+
+    // Every aunt, by how many predicates match and then by how few fail, so near
+    // misses still show up when nobody matches outright.
+    pub fn rank<'a>(&'a self, aunts: &[HashMap<String, i32>]) -> Vec<Match<'a>> {
+        let mut matches = aunts.iter().enumerate().map(|(i, props)| self.check(i, props)).collect::<Vec<_>>();
+        matches.sort_by_key(|m| (Reverse(m.matched.len()), m.failed.len(), m.aunt));
+        matches
+    }
+}
+
+#[test]
+fn test_parse() {
+    let q = "cats>7, trees>3, pomeranians<3, akitas=0, cars>=2, vizslas<=1, perfumes!=4, goldfish==5".parse::<Query>().unwrap();
+    let cmps = q.0.iter().map(|p| p.cmp).collect::<Vec<_>>();
+    assert_eq!(cmps, vec![Cmp::Gt, Cmp::Gt, Cmp::Lt, Cmp::Eq, Cmp::Ge, Cmp::Le, Cmp::Ne, Cmp::Eq]);
+    assert_eq!(q.0[0], Predicate { prop: "cats".to_string(), cmp: Cmp::Gt, value: 7 });
+    assert_eq!(q.0[4].to_string(), "cars>=2");
+    assert_eq!(q.0[7].to_string(), "goldfish=5");
+    assert_eq!("".parse::<Query>().unwrap(), Query(vec![]));
+
+    assert!("cats".parse::<Query>().is_err());
+    assert!("cats>seven".parse::<Query>().is_err());
+    assert!(">7".parse::<Query>().is_err());
+    assert!("cats=>7".parse::<Query>().is_err());
+}
+
+#[test]
+fn test_rank() {
+    let aunt = |props: &[(&str, i32)]| props.iter().map(|&(p, a)| (p.to_string(), a)).collect::<HashMap<_, _>>();
+    let aunts = vec![
+        aunt(&[("cats", 9), ("trees", 1)]),
+        aunt(&[("cats", 8), ("akitas", 0), ("trees", 5)]),
+        aunt(&[("goldfish", 3)]),
+        aunt(&[("cats", 9), ("akitas", 0)]),
+    ];
+    let q = "cats>7, trees>3, akitas=0".parse::<Query>().unwrap();
+    let ranked = q.rank(&aunts);
+    assert_eq!(ranked.iter().map(|m| m.aunt).collect::<Vec<_>>(), vec![1, 3, 0, 2]);
+    assert_eq!((ranked[0].matched.len(), ranked[0].failed.len()), (3, 0));
+    assert_eq!(ranked[2].failed, vec![&q.0[1]]);
+    assert!(ranked[3].exact() && ranked[3].matched.is_empty());
+}
@@ -1,72 +1,35 @@
-use anyhow::{anyhow, Result};
+use std::io;
 
-struct Package {
-    l: u32,
-    w: u32,
-    h: u32,
-}
-
-type Side = (u32, u32);
-
-fn area(side: &Side) -> u32 {
-    side.0 * side.1
-}
-
-fn perimeter(side: &Side) -> u32 {
-    2 * (side.0 + side.1)
-}
+mod shape;
 
-impl Package {
-    fn sides(&self) -> Vec<Side> {
-        [(self.l, self.w),
-         (self.l, self.w),
-         (self.l, self.h),
-         (self.l, self.h),
-         (self.w, self.h),
-         (self.w, self.h)].to_vec()
-    }
+use shape::{amount, parse_order, write_report, Shape};
 
-    fn paper_needed(&self) -> u32 {
-        self.sides().iter().map(area).sum::<u32>() +
-            self.sides().iter().map(area).min().unwrap_or(0)
+fn main() -> anyhow::Result<()> {
+    // an order file instead of the puzzle input, and --report anywhere (or just
+    // "report" on its own) for the itemised version
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let alone = args == ["report"];
+    let report = alone || args.iter().any(|a| a == "--report");
+    let mut paths = args.iter().filter(|a| *a != "--report" && !alone);
+    let path = paths.next();
+    if let Some(extra) = paths.next() {
+        return Err(anyhow::anyhow!("unexpected argument {:?}, only one order file can be read", extra));
     }
-
-    fn ribbon_needed(&self) -> u32 {
-        self.sides().iter().map(perimeter).min().unwrap_or(0) +
-            (self.l * self.w * self.h)
+    let src = match path {
+        Some(path) => std::fs::read_to_string(path)?,
+        None => include_str!("input.txt").to_string(),
+    };
+    let packages = parse_order(&src)?;
+
+    if report {
+        write_report(&packages, &mut io::stdout())?;
     }
-}
-
-#[test]
-fn package_needed() {
-    assert_eq!(Package{l: 2, w: 3, h: 4}.paper_needed(), 58);
-    assert_eq!(Package{l: 2, w: 3, h: 4}.ribbon_needed(), 34);
-    assert_eq!(Package{l: 1, w: 1, h: 10}.ribbon_needed(), 14);
-
-}
-
-fn parse_package(line: &str) -> Result<Package> {
-    let dims : Vec<u32> = line.split("x")
-                              .map(str::parse::<u32>)
-                              .collect::<Result<_, _>>()?;
-    if dims.len() != 3 {
-        return Err(anyhow!("Expected 3 dimensions, got {}", dims.len()))
-    }
-
-    Ok(Package {l: dims[0], w: dims[1], h: dims[2]})
-}
-
-fn main() -> anyhow::Result<()> {
-    let packages = include_str!("input.txt")
-        .split("\n")
-        .map(parse_package)
-        .collect::<Result<Vec<Package>, _>>()?;
 
-    let paper_needed: u32 = packages.iter().map(Package::paper_needed).sum();
-    let ribbon_needed: u32 = packages.iter().map(Package::ribbon_needed).sum();
+    let paper_needed: f64 = packages.iter().map(Shape::paper).sum();
+    let ribbon_needed: f64 = packages.iter().map(Shape::ribbon).sum();
 
-    println!("Paper needed: {} sq ft", paper_needed);
-    println!("Ribbon needed: {} ft", ribbon_needed);
+    println!("Paper needed: {} sq ft", amount(paper_needed));
+    println!("Ribbon needed: {} ft", amount(ribbon_needed));
 
     Ok(())
 }
@@ -0,0 +1,221 @@
+use std::{f64::consts::PI, fmt, io::Write, str::FromStr};
+
+use anyhow::{anyhow, Result};
+
+// Every shape needs its surface in paper plus a little slack the size of its
+// smallest face, and ribbon around its smallest perimeter plus a bow as long as
+// its volume.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Shape {
+    Box { l: f64, w: f64, h: f64 },
+    Cube { side: f64 },
+    Cylinder { radius: f64, height: f64 },
+    // a triangle with sides a, b and c pulled out to length
+    Prism { a: f64, b: f64, c: f64, length: f64 },
+}
+
+fn min(xs: &[f64]) -> f64 {
+    xs.iter().copied().fold(f64::INFINITY, f64::min)
+}
+
+impl Shape {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Shape::Box { .. } => "box",
+            Shape::Cube { .. } => "cube",
+            Shape::Cylinder { .. } => "cylinder",
+            Shape::Prism { .. } => "prism",
+        }
+    }
+
+    // the numbers it was made from, in the order they're written
+    pub fn dims(&self) -> Vec<f64> {
+        match *self {
+            Shape::Box { l, w, h } => vec![l, w, h],
+            Shape::Cube { side } => vec![side],
+            Shape::Cylinder { radius, height } => vec![radius, height],
+            Shape::Prism { a, b, c, length } => vec![a, b, c, length],
+        }
+    }
+
+    pub fn new(name: &str, dims: &[f64]) -> Result<Shape> {
+        if let Some(d) = dims.iter().find(|d| !(d.is_finite() && **d > 0.0)) {
+            return Err(anyhow!("{} isn't a size a {} can have", d, name));
+        }
+        let shape = match (name, dims) {
+            ("box", &[l, w, h]) => Shape::Box { l, w, h },
+            ("cube", &[side]) => Shape::Cube { side },
+            ("cylinder", &[radius, height]) => Shape::Cylinder { radius, height },
+            ("prism", &[a, b, c, length]) => {
+                if a + b <= c || a + c <= b || b + c <= a {
+                    return Err(anyhow!("{}, {} and {} don't make a triangle", a, b, c));
+                }
+                Shape::Prism { a, b, c, length }
+            },
+            ("box", _) | ("cube", _) | ("cylinder", _) | ("prism", _) => {
+                return Err(anyhow!("wrong number of sizes for a {}: {:?}", name, dims))
+            },
+            _ => return Err(anyhow!("don't know how to wrap a {:?}", name)),
+        };
+        Ok(shape)
+    }
+
+    // Heron's
+    fn triangle(a: f64, b: f64, c: f64) -> f64 {
+        let s = (a + b + c) / 2.0;
+        (s * (s - a) * (s - b) * (s - c)).sqrt()
+    }
+
+    pub fn paper(&self) -> f64 {
+        match *self {
+            Shape::Box { l, w, h } => {
+                let (lw, lh, wh) = (l * w, l * h, w * h);
+                2.0 * (lw + lh + wh) + min(&[lw, lh, wh])
+            },
+            Shape::Cube { side } => 7.0 * side * side,
+            Shape::Cylinder { radius, height } => {
+                let (cap, wall) = (PI * radius * radius, 2.0 * PI * radius * height);
+                2.0 * cap + wall + min(&[cap, wall])
+            },
+            Shape::Prism { a, b, c, length } => {
+                let end = Shape::triangle(a, b, c);
+                2.0 * end + (a + b + c) * length + min(&[end, a.min(b).min(c) * length])
+            },
+        }
+    }
+
+    pub fn ribbon(&self) -> f64 {
+        match *self {
+            Shape::Box { l, w, h } => 2.0 * min(&[l + w, l + h, w + h]) + l * w * h,
+            Shape::Cube { side } => 4.0 * side + side * side * side,
+            // round the middle or the long way over the ends
+            Shape::Cylinder { radius, height } => {
+                min(&[2.0 * PI * radius, 2.0 * (2.0 * radius + height)]) + PI * radius * radius * height
+            },
+            Shape::Prism { a, b, c, length } => {
+                let around = min(&[a + b + c, 2.0 * (a.min(b).min(c) + length)]);
+                around + Shape::triangle(a, b, c) * length
+            },
+        }
+    }
+}
+
+impl fmt::Display for Shape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let dims = self.dims().iter().map(|d| d.to_string()).collect::<Vec<_>>();
+        write!(f, "{} {}", self.name(), dims.join("x"))
+    }
+}
+
+// An LxWxH box, or a shape name then its sizes separated by commas or tabs,
+// like "cylinder,2,10" or "prism\t3\t4\t5\t10".
+impl FromStr for Shape {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        // whichever separator comes first is the one the line uses
+        let fields = match s.find([',', '\t']) {
+            Some(at) => s.split(s.as_bytes()[at] as char).map(str::trim).collect::<Vec<_>>(),
+            None => {
+                let mut fields = vec!["box"];
+                fields.extend(s.trim().split('x'));
+                fields
+            },
+        };
+        let dims = fields[1..].iter()
+            .map(|d| d.parse::<f64>().map_err(|_| anyhow!("bad size {:?} in {:?}", d, s)))
+            .collect::<Result<Vec<_>>>()?;
+        Shape::new(&fields[0].to_lowercase(), &dims)
+    }
+}
+
+// Every line of an order that isn't blank or a "shape,..." header.
+pub fn parse_order(src: &str) -> Result<Vec<Shape>> {
+    src.lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty() && !l.trim_start().to_lowercase().starts_with("shape"))
+        .map(|(i, l)| l.parse().map_err(|e| anyhow!("line {}: {}", i + 1, e)))
+        .collect()
+}
+
+// Whole numbers print as they are, anything else to a couple of places.
+pub fn amount(x: f64) -> String {
+    if x.fract() == 0.0 {
+        format!("{}", x)
+    } else {
+        format!("{:.2}", x)
+    }
+}
+
+// one line a package with what it takes, then the totals
+pub fn write_report(shapes: &[Shape], out: &mut impl Write) -> Result<()> {
+    writeln!(out, "{:>5}  {:<24} {:>12} {:>12}", "#", "package", "paper", "ribbon")?;
+    for (i, shape) in shapes.iter().enumerate() {
+        writeln!(out, "{:>5}  {:<24} {:>12} {:>12}", i + 1, shape.to_string(), amount(shape.paper()), amount(shape.ribbon()))?;
+    }
+    let paper = shapes.iter().map(Shape::paper).sum::<f64>();
+    let ribbon = shapes.iter().map(Shape::ribbon).sum::<f64>();
+    writeln!(out, "{:>5}  {:<24} {:>12} {:>12}", "", "total", amount(paper), amount(ribbon))?;
+    Ok(())
+}
+
+#[test]
+fn package_needed() {
+    assert_eq!(Shape::Box { l: 2.0, w: 3.0, h: 4.0 }.paper(), 58.0);
+    assert_eq!(Shape::Box { l: 2.0, w: 3.0, h: 4.0 }.ribbon(), 34.0);
+    assert_eq!(Shape::Box { l: 1.0, w: 1.0, h: 10.0 }.ribbon(), 14.0);
+}
+
+#[test]
+fn test_shapes() {
+    let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+    let cube = "cube,3".parse::<Shape>().unwrap();
+    assert_eq!(cube, Shape::Cube { side: 3.0 });
+    assert_eq!((cube.paper(), cube.ribbon()), ("3x3x3".parse::<Shape>().unwrap().paper(), 39.0));
+
+    let can = "cylinder\t1\t2".parse::<Shape>().unwrap();
+    assert!(close(can.paper(), 7.0 * PI));
+    assert!(close(can.ribbon(), 2.0 * PI + 2.0 * PI));
+    // a squat can gets tied the long way
+    let squat = Shape::Cylinder { radius: 10.0, height: 1.0 };
+    assert!(close(squat.ribbon(), 42.0 + 100.0 * PI));
+    // and its slack is the wall, which is smaller than an end
+    assert!(close(squat.paper(), 200.0 * PI + 20.0 * PI + 20.0 * PI));
+
+    // 3-4-5 triangles have area 6
+    let prism = "prism, 3, 4, 5, 10".parse::<Shape>().unwrap();
+    assert!(close(prism.paper(), 12.0 + 120.0 + 6.0));
+    assert!(close(prism.ribbon(), 12.0 + 60.0));
+    let long = Shape::Prism { a: 3.0, b: 4.0, c: 5.0, length: 1.0 };
+    assert!(close(long.paper(), 12.0 + 12.0 + 3.0));
+    assert!(close(long.ribbon(), 8.0 + 6.0));
+    assert_eq!(prism.to_string(), "prism 3x4x5x10");
+}
+
+#[test]
+fn test_bad_shapes() {
+    assert!("2x3".parse::<Shape>().is_err());
+    assert!("2x3xfour".parse::<Shape>().is_err());
+    assert!("2x0x4".parse::<Shape>().is_err());
+    assert!("cube,1,2".parse::<Shape>().is_err());
+    assert!("sphere,2".parse::<Shape>().is_err());
+    assert!("prism,1,2,5,10".parse::<Shape>().is_err());
+    assert!("cylinder,-1,2".parse::<Shape>().is_err());
+}
+
+#[test]
+fn test_order() {
+    let order = "shape,sizes...\n2x3x4\nCube,1\n\ncylinder\t1\t1\nbox,1,1,10\n";
+    let shapes = parse_order(order).unwrap();
+    assert_eq!(shapes.len(), 4);
+    assert_eq!(shapes[3], Shape::Box { l: 1.0, w: 1.0, h: 10.0 });
+    assert_eq!(parse_order("2x3x4\nbox,2\n").unwrap_err().to_string(), "line 2: wrong number of sizes for a box: [2.0]");
+
+    let mut report = vec![];
+    write_report(&shapes[..2], &mut report).unwrap();
+    let report = String::from_utf8(report).unwrap();
+    let lines = report.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 4);
+    assert!(lines[1].contains("box 2x3x4") && lines[1].ends_with("58           34"));
+    assert!(lines[3].contains("total") && lines[3].ends_with("65           39"));
+    assert_eq!((amount(58.0), amount(PI)), ("58".to_string(), "3.14".to_string()));
+}
@@ -1,4 +1,8 @@
-use std::collections::HashMap;
+use std::{fs::File, io::{self, BufWriter, Write}};
+
+mod visits;
+
+use visits::{Heatmap, Order, Visits};
 
 type Coord = (i32, i32);
 
@@ -12,18 +16,42 @@ fn scoot(c: Coord, dir: char) -> Coord {
     }
 }
 
+// most "at least k" lines to print
+const MAX_K: u32 = 5;
+
 fn main() -> anyhow::Result<()> {
-    let mut g : HashMap<Coord, u32> = HashMap::new();
-    let mut santas = [(0, 0), (0, 0)];
-    g.insert(santas[0], 2);
-
-    for dir in include_str!("input.txt").chars() {
-        santas[0] = scoot(santas[0], dir);
-        *g.entry(santas[0]).or_insert(0) += 1;
-        santas.reverse();
+    // agents is either how many take turns round robin, or an explicit order like 0,0,1
+    let args = std::env::args().collect::<Vec<_>>();
+    let order = match args.get(1) {
+        Some(a) if a.contains(',') => a.parse()?,
+        Some(a) => Order::round_robin(a.parse()?),
+        None => Order::round_robin(2),
+    };
+    let visits = Visits::follow(include_str!("input.txt"), &order)?;
+
+    println!("{} agents taking turns {:?}", visits.agents, order.0);
+    for agent in 0..visits.agents {
+        println!("  agent {} went to {} houses", agent, visits.by_agent(agent));
+    }
+    for k in 1..=MAX_K {
+        println!("At least {} : {}", k, visits.at_least(k));
     }
+    println!("By every agent : {}", visits.by_every_agent());
 
-    println!("At least 1 : {}", g.values().len());
+    // .pgm gets an image, anything else the ascii version, - for stdout
+    if let Some(path) = args.get(2) {
+        let map = Heatmap::new(&visits.totals());
+        let mut out: Box<dyn Write> = match path.as_str() {
+            "-" => Box::new(io::stdout()),
+            _ => Box::new(BufWriter::new(File::create(path)?)),
+        };
+        if path.ends_with(".pgm") {
+            map.write_pgm(&mut out)?;
+        } else {
+            map.write_ascii(&mut out)?;
+        }
+        println!("{}x{} heatmap, busiest house {} visits", map.width, map.height, map.busiest);
+    }
 
     Ok(())
 }
@@ -0,0 +1,190 @@
+use std::{collections::HashMap, io::Write, str::FromStr};
+
+use anyhow::{anyhow, Result};
+
+use crate::{scoot, Coord};
+
+// Who moves on each turn, over and over. "0,1" is santa then robo-santa;
+// "0,0,1" gives santa two moves for every one of robo-santa's.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Order(pub Vec<usize>);
+
+impl Order {
+    pub fn round_robin(agents: usize) -> Order {
+        Order((0..agents).collect())
+    }
+
+    // one past the highest agent that ever gets a turn
+    pub fn agents(&self) -> usize {
+        self.0.iter().max().map_or(0, |&a| a + 1)
+    }
+
+    // Every agent up to the highest has to get a turn, or one would sit at the
+    // origin the whole time and only count there.
+    fn check(&self) -> Result<()> {
+        if self.0.is_empty() {
+            return Err(anyhow!("nobody to do the moving"));
+        }
+        match (0..self.agents()).find(|a| !self.0.contains(a)) {
+            Some(a) => Err(anyhow!("agent {} never gets a turn in {:?}", a, self.0)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl FromStr for Order {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let turns = s.split(',').map(|t| t.trim().parse::<usize>()).collect::<Result<Vec<_>, _>>()
+            .map_err(|_| anyhow!("turn order should be agent numbers like 0,1,1 not {:?}", s))?;
+        let order = Order(turns);
+        order.check()?;
+        Ok(order)
+    }
+}
+
+// how many times each agent has been to each house
+pub struct Visits {
+    pub agents: usize,
+    pub houses: HashMap<Coord, Vec<u32>>,
+}
+
+impl Visits {
+    // Everyone starts out delivering at the origin, then the moves get handed
+    // out by order. Whitespace is skipped but anything else that isn't an arrow
+    // is an error.
+    pub fn follow(moves: &str, order: &Order) -> Result<Visits> {
+        order.check()?;
+        let agents = order.agents();
+        let mut houses = HashMap::new();
+        houses.insert((0, 0), vec![1; agents]);
+        let mut at = vec![(0, 0); agents];
+        let mut turns = order.0.iter().cycle();
+        for (i, dir) in moves.chars().enumerate().filter(|(_, c)| !c.is_whitespace()) {
+            if !"^v<>".contains(dir) {
+                return Err(anyhow!("bad move {:?} at {}", dir, i));
+            }
+            let agent = *turns.next().unwrap();
+            at[agent] = scoot(at[agent], dir);
+            houses.entry(at[agent]).or_insert_with(|| vec![0; agents])[agent] += 1;
+        }
+        Ok(Visits { agents, houses })
+    }
+
+    // everybody's visits to each house added up
+    pub fn totals(&self) -> HashMap<Coord, u32> {
+        self.houses.iter().map(|(&c, v)| (c, v.iter().sum())).collect()
+    }
+
+    pub fn at_least(&self, k: u32) -> usize {
+        self.houses.values().filter(|v| v.iter().sum::<u32>() >= k).count()
+    }
+
+    pub fn by_agent(&self, agent: usize) -> usize {
+        self.houses.values().filter(|v| v[agent] > 0).count()
+    }
+
+    pub fn by_every_agent(&self) -> usize {
+        self.houses.values().filter(|v| v.iter().all(|&n| n > 0)).count()
+    }
+}
+
+// Visit counts laid out with north up, one cell per house across the bounding
+// box of the houses that got anything. Values are scaled so the busiest house is
+// the top of the range.
+pub struct Heatmap {
+    pub width: usize,
+    pub height: usize,
+    pub cells: Vec<u32>,
+    pub busiest: u32,
+}
+
+impl Heatmap {
+    pub fn new(counts: &HashMap<Coord, u32>) -> Heatmap {
+        let (x0, x1) = (counts.keys().map(|c| c.0).min().unwrap_or(0), counts.keys().map(|c| c.0).max().unwrap_or(0));
+        let (y0, y1) = (counts.keys().map(|c| c.1).min().unwrap_or(0), counts.keys().map(|c| c.1).max().unwrap_or(0));
+        let (width, height) = ((x1 - x0 + 1) as usize, (y1 - y0 + 1) as usize);
+        let mut cells = vec![0; width * height];
+        for (&(x, y), &n) in counts {
+            cells[(y1 - y) as usize * width + (x - x0) as usize] = n;
+        }
+        let busiest = cells.iter().copied().max().unwrap_or(0);
+        Heatmap { width, height, cells, busiest }
+    }
+
+    // 0 for houses nobody went to, then up to top
+    fn scaled(&self, n: u32, top: u32) -> u32 {
+        match (n, self.busiest) {
+            (0, _) => 0,
+            (_, 1) => top,
+            _ => 1 + ((n as u64 - 1) * (top as u64 - 1) / (self.busiest as u64 - 1)) as u32,
+        }
+    }
+
+    pub fn write_ascii(&self, out: &mut impl Write) -> Result<()> {
+        const SHADES: &[u8] = b" .:-=+*#%@";
+        for row in self.cells.chunks(self.width) {
+            let line = row.iter().map(|&n| SHADES[self.scaled(n, SHADES.len() as u32 - 1) as usize]).collect::<Vec<_>>();
+            out.write_all(&line)?;
+            out.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    // binary P5, brighter is busier
+    pub fn write_pgm(&self, out: &mut impl Write) -> Result<()> {
+        write!(out, "P5\n{} {}\n255\n", self.width, self.height)?;
+        let pixels = self.cells.iter().map(|&n| self.scaled(n, 255) as u8).collect::<Vec<_>>();
+        out.write_all(&pixels)?;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_examples() {
+    let santa = Order::round_robin(1);
+    let both = Order::round_robin(2);
+    assert_eq!(Visits::follow(">", &santa).unwrap().at_least(1), 2);
+    assert_eq!(Visits::follow("^>v<", &santa).unwrap().at_least(1), 4);
+    assert_eq!(Visits::follow("^v^v^v^v^v", &santa).unwrap().at_least(1), 2);
+    assert_eq!(Visits::follow("^v", &both).unwrap().at_least(1), 3);
+    assert_eq!(Visits::follow("^>v<", &both).unwrap().at_least(1), 3);
+    assert_eq!(Visits::follow("^v^v^v^v^v", &both).unwrap().at_least(1), 11);
+}
+
+#[test]
+fn test_queries() {
+    // santa goes up twice for every time robo-santa goes right
+    let order = "0,0,1".parse::<Order>().unwrap();
+    let visits = Visits::follow("^v>^v<\n", &order).unwrap();
+    assert_eq!(visits.agents, 2);
+    assert_eq!(visits.houses[&(0, 0)], vec![3, 2]);
+    assert_eq!(visits.houses[&(1, 0)], vec![0, 1]);
+    assert_eq!(visits.houses[&(0, 1)], vec![2, 0]);
+    assert_eq!((visits.by_agent(0), visits.by_agent(1)), (2, 2));
+    assert_eq!(visits.by_every_agent(), 1);
+    assert_eq!((visits.at_least(1), visits.at_least(2), visits.at_least(5), visits.at_least(6)), (3, 2, 1, 0));
+    assert_eq!(visits.totals()[&(0, 0)], 5);
+
+    assert!(Visits::follow("^x", &order).is_err());
+    assert!(Visits::follow("^", &Order::round_robin(0)).is_err());
+    assert!("".parse::<Order>().is_err());
+    assert!("0,santa".parse::<Order>().is_err());
+    assert_eq!("0,2".parse::<Order>().unwrap_err().to_string(), "agent 1 never gets a turn in [0, 2]");
+    assert!("1,1".parse::<Order>().is_err());
+    assert!(Visits::follow("^", &Order(vec![0, 2])).is_err());
+}
+
+#[test]
+fn test_heatmap() {
+    let visits = Visits::follow("^>v<^^", &Order::round_robin(1)).unwrap();
+    let map = Heatmap::new(&visits.totals());
+    assert_eq!((map.width, map.height, map.busiest), (2, 3, 2));
+    let mut ascii = vec![];
+    map.write_ascii(&mut ascii).unwrap();
+    assert_eq!(String::from_utf8(ascii).unwrap(), ". \n@.\n@.\n");
+
+    let mut pgm = vec![];
+    map.write_pgm(&mut pgm).unwrap();
+    assert_eq!(pgm, b"P5\n2 3\n255\n\x01\x00\xff\x01\xff\x01".to_vec());
+}
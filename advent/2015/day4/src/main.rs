@@ -1,15 +1,124 @@
+use std::{sync::atomic::{AtomicU32, AtomicU64, Ordering}, thread};
+
+use anyhow::{anyhow, Result};
+
 const KEY: &str = "ckczppom";
+// how many candidates a thread takes at a time
+const BLOCK: u64 = 1 << 16;
+// an md5 digest is only 32 hex digits long
+const MAX_ZEROS: usize = 32;
+
+fn parse_zeros(s: &str) -> Result<usize> {
+    let zeros = s.parse::<usize>()?;
+    if zeros > MAX_ZEROS {
+        return Err(anyhow!("a digest only has {} hex digits, so {} zeros can't happen", MAX_ZEROS, zeros));
+    }
+    Ok(zeros)
+}
+
+fn leading_zeros(digest: &[u8; 16], zeros: usize) -> bool {
+    let (bytes, nibble) = (zeros / 2, zeros % 2);
+    digest[..bytes].iter().all(|&b| b == 0) && (nibble == 0 || digest[bytes] >> 4 == 0)
+}
+
+// decimal digits of n into the end of buf, without going through a String
+fn digits(mut n: u32, buf: &mut [u8; 10]) -> &[u8] {
+    let mut i = buf.len();
+    loop {
+        i -= 1;
+        buf[i] = b'0' + (n % 10) as u8;
+        n /= 10;
+        if n == 0 {
+            return &buf[i..];
+        }
+    }
+}
+
+fn is_coin(prefix: &md5::Context, n: u32, zeros: usize) -> bool {
+    let mut buf = [0; 10];
+    let mut ctx = prefix.clone();
+    ctx.consume(digits(n, &mut buf));
+    leading_zeros(&ctx.compute(), zeros)
+}
+
+// Threads pull blocks off a shared counter and keep the lowest coin found so far.
+// Blocks starting past that can't hold anything lower, so nobody bothers with them.
+fn mine(key: &str, zeros: usize) -> Option<u32> {
+    let mut prefix = md5::Context::new();
+    prefix.consume(key.as_bytes());
+    let next = AtomicU64::new(0);
+    let best = AtomicU32::new(u32::MAX);
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+
+    thread::scope(|s| {
+        for _ in 0..threads {
+            s.spawn(|| loop {
+                let start = next.fetch_add(BLOCK, Ordering::Relaxed);
+                if start > u32::MAX as u64 || start >= best.load(Ordering::Relaxed) as u64 {
+                    return;
+                }
+                let end = (start + BLOCK).min(u32::MAX as u64 + 1);
+                if let Some(n) = (start..end).map(|n| n as u32).find(|&n| is_coin(&prefix, n, zeros)) {
+                    best.fetch_min(n, Ordering::Relaxed);
+                    return;
+                }
+            });
+        }
+    });
+
+    // u32::MAX itself would only show up here if it really is the first coin
+    let best = best.into_inner();
+    (best != u32::MAX || is_coin(&prefix, best, zeros)).then_some(best)
+}
+
+#[test]
+fn test_leading_zeros() {
+    let mut digest = [0xff; 16];
+    assert!(leading_zeros(&digest, 0));
+    assert!(!leading_zeros(&digest, 1));
+    digest[0] = 0;
+    digest[1] = 0x0f;
+    assert!(leading_zeros(&digest, 2));
+    assert!(leading_zeros(&digest, 3));
+    assert!(!leading_zeros(&digest, 4));
+    assert!(leading_zeros(&[0; 16], MAX_ZEROS));
+    let mut digest = [0; 16];
+    digest[15] = 1;
+    assert!(leading_zeros(&digest, 31));
+    assert!(!leading_zeros(&digest, MAX_ZEROS));
+}
+
+#[test]
+fn test_parse_zeros() {
+    assert_eq!(parse_zeros("5").unwrap(), 5);
+    assert_eq!(parse_zeros("32").unwrap(), MAX_ZEROS);
+    assert!(parse_zeros("33").is_err());
+    assert!(parse_zeros("34").is_err());
+    assert!(parse_zeros("-1").is_err());
+}
 
-fn is_coin(n: u32, prefix: &str) -> bool {
-    let s = format!("{}{}", KEY, n);
-    format!("{:x}", md5::compute(s)).starts_with(prefix)
+#[test]
+fn test_digits() {
+    let mut buf = [0; 10];
+    assert_eq!(digits(0, &mut buf), b"0");
+    assert_eq!(digits(609043, &mut buf), b"609043");
+    assert_eq!(digits(u32::MAX, &mut buf), b"4294967295");
 }
 
-fn coins(prefix: &str) -> impl Iterator<Item = u32> + '_ {
-    (0..0xFFFFFFFFu32).filter(move |n| is_coin(*n, prefix))
+#[test]
+fn test_mine() {
+    assert_eq!(mine("abcdef", 5), Some(609043));
+    assert_eq!(mine("pqrstuv", 5), Some(1048970));
+    assert_eq!(mine("abcdef", 1), Some(31));
 }
 
-fn main() {
-    let first = coins("00000").nth(0).expect("no coins in u32?!");
-    println!("First coin {}", first);
+fn main() -> Result<()> {
+    let args = std::env::args().collect::<Vec<_>>();
+    let key = args.get(1).map_or(KEY, |k| k.as_str());
+    let zeros = args.get(2).map_or(Ok(5), |z| parse_zeros(z))?;
+    match mine(key, zeros) {
+        Some(first) => println!("First coin {}", first),
+        None => println!("no coins in u32?!"),
+    }
+    Ok(())
 }
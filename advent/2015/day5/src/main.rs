@@ -1,3 +1,15 @@
+use rules::Rule;
+
+mod rules;
+
+const NICE: &str = "all(at_least(3, aeiou), repeat(0), not(any_of(ab, cd, pq, xy)))";
+const NICE2: &str = "all(repeat(1), pair_twice)";
+
+// how many naughty lines to explain for a policy given on the command line
+const SHOW: usize = 5;
+
+// the hand written checks the policies started out as
+#[cfg(test)]
 fn three_vowels(s: &str) -> bool {
     let mut vowels = 0;
     //filter?
@@ -15,6 +27,7 @@ fn three_vowels(s: &str) -> bool {
     false
 }
 
+#[cfg(test)]
 fn doubled_char(s: &str) -> bool {
     let chars = s.chars().collect::<Vec<char>>();
     for pair in chars.windows(2) {
@@ -25,6 +38,7 @@ fn doubled_char(s: &str) -> bool {
     false
 }
 
+#[cfg(test)]
 fn no_bad_strings(s: &str) -> bool {
     let chars = s.chars().collect::<Vec<char>>();
     for pair in chars.windows(2) {
@@ -38,6 +52,7 @@ fn no_bad_strings(s: &str) -> bool {
     true
 }
 
+#[cfg(test)]
 fn nice(s: &str) -> bool {
     three_vowels(s) && doubled_char(s) && no_bad_strings(s)
 }
@@ -53,6 +68,7 @@ fn nice_test() {
     assert!(!nice("dvszwmarrgswjxmb"));
 }
 
+#[cfg(test)]
 fn split_repeat(s: &str) -> bool {
     let chars = s.chars().collect::<Vec<char>>();
     for triple in chars.windows(3) {
@@ -63,6 +79,7 @@ fn split_repeat(s: &str) -> bool {
     false
 }
 
+#[cfg(test)]
 fn separate_pairs(s: &str) -> bool {
     let chars = s.chars().collect::<Vec<char>>();
     for (i, pair) in chars.windows(2).enumerate() {
@@ -75,6 +92,7 @@ fn separate_pairs(s: &str) -> bool {
     false
 }
 
+#[cfg(test)]
 fn nice2(s: &str) -> bool {
     split_repeat(s) && separate_pairs(s)
 }
@@ -96,13 +114,34 @@ fn nice2_test() {
     assert!(!nice2("ieodomkazucvgmuy"));
 }
 
-fn main() {
+#[test]
+fn test_policies() {
+    let (nice_rule, nice2_rule) = (NICE.parse::<Rule>().unwrap(), NICE2.parse::<Rule>().unwrap());
+    for line in include_str!("input.txt").lines() {
+        assert_eq!(nice_rule.nice(line), nice(line), "{}", line);
+        assert_eq!(nice2_rule.nice(line), nice2(line), "{}", line);
+    }
+}
+
+fn main() -> anyhow::Result<()> {
     let input = include_str!("input.txt");
+    let (nice, nice2) = (NICE.parse::<Rule>()?, NICE2.parse::<Rule>()?);
     let nice = input.lines().
-        filter(|l| nice(l)).
+        filter(|l| nice.nice(l)).
         count();
     let nice2 = input.lines().
-        filter(|l| nice2(l)).
+        filter(|l| nice2.nice(l)).
         count();
     println!("Nice: {} Nice 2: {}", nice, nice2);
+
+    // any other policy, with why the first few naughty lines were naughty
+    if let Some(policy) = std::env::args().nth(1) {
+        let policy = policy.parse::<Rule>()?;
+        let verdicts = input.lines().map(|l| (l, policy.check(l))).collect::<Vec<_>>();
+        println!("{}: {}", policy, verdicts.iter().filter(|(_, v)| v.is_ok()).count());
+        for (line, why) in verdicts.iter().filter_map(|(l, v)| v.as_ref().err().map(|why| (l, why))).take(SHOW) {
+            println!("  {} is naughty, {}", line, why);
+        }
+    }
+    Ok(())
 }
@@ -0,0 +1,303 @@
+use std::{collections::HashMap, fmt, iter::Peekable, str::FromStr, vec::IntoIter};
+
+use anyhow::{anyhow, Result};
+
+// A naughty/nice policy. Written out it looks like
+// all(at_least(3, aeiou), repeat(0), not(any_of(ab, cd, pq, xy)))
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Rule {
+    // at least n chars that are in the set, counting repeats
+    AtLeast(usize, Vec<char>),
+    // some char shows up again with gap chars in between; 0 is a double like "xx"
+    Repeat(usize),
+    // one of these bigrams shows up somewhere
+    AnyOf(Vec<[char; 2]>),
+    // some pair of chars shows up twice without the two overlapping
+    PairTwice,
+    All(Vec<Rule>),
+    Any(Vec<Rule>),
+    Not(Box<Rule>),
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let list = |f: &mut fmt::Formatter<'_>, name: &str, rules: &[Rule]| {
+            let rules = rules.iter().map(|r| r.to_string()).collect::<Vec<_>>();
+            write!(f, "{}({})", name, rules.join(", "))
+        };
+        match self {
+            Rule::AtLeast(n, set) => write!(f, "at_least({}, {})", n, set.iter().collect::<String>()),
+            Rule::Repeat(gap) => write!(f, "repeat({})", gap),
+            Rule::AnyOf(bigrams) => {
+                let bigrams = bigrams.iter().map(|b| b.iter().collect::<String>()).collect::<Vec<_>>();
+                write!(f, "any_of({})", bigrams.join(", "))
+            },
+            Rule::PairTwice => write!(f, "pair_twice"),
+            Rule::All(rules) => list(f, "all", rules),
+            Rule::Any(rules) => list(f, "any", rules),
+            Rule::Not(rule) => write!(f, "not({})", rule),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    Open,
+    Close,
+    Comma,
+}
+
+fn tokens(s: &str) -> Result<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            ',' => tokens.push(Token::Comma),
+            c if c.is_whitespace() => (),
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut word = c.to_string();
+                while let Some(&c) = chars.peek().filter(|c| c.is_alphanumeric() || **c == '_') {
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            },
+            _ => return Err(anyhow!("unexpected {:?} in rule", c)),
+        }
+    }
+    Ok(tokens)
+}
+
+type Tokens = Peekable<IntoIter<Token>>;
+
+fn expect(tokens: &mut Tokens, want: Token) -> Result<()> {
+    match tokens.next() {
+        Some(t) if t == want => Ok(()),
+        t => Err(anyhow!("expected {:?} but got {:?}", want, t)),
+    }
+}
+
+fn word(tokens: &mut Tokens) -> Result<String> {
+    match tokens.next() {
+        Some(Token::Word(w)) => Ok(w),
+        t => Err(anyhow!("expected a word but got {:?}", t)),
+    }
+}
+
+// whatever's between the parens, separated by commas
+fn args<T>(tokens: &mut Tokens, mut arg: impl FnMut(&mut Tokens) -> Result<T>) -> Result<Vec<T>> {
+    expect(tokens, Token::Open)?;
+    let mut args = vec![arg(tokens)?];
+    while tokens.peek() == Some(&Token::Comma) {
+        tokens.next();
+        args.push(arg(tokens)?);
+    }
+    expect(tokens, Token::Close)?;
+    Ok(args)
+}
+
+fn rule(tokens: &mut Tokens) -> Result<Rule> {
+    let name = word(tokens)?;
+    let rule = match name.as_str() {
+        "at_least" => match args(tokens, word)?.as_slice() {
+            [n, set] => Rule::AtLeast(n.parse()?, set.chars().collect()),
+            _ => return Err(anyhow!("at_least takes a count and a set of chars")),
+        },
+        "repeat" => match args(tokens, word)?.as_slice() {
+            [gap] => Rule::Repeat(gap.parse()?),
+            _ => return Err(anyhow!("repeat takes just a gap")),
+        },
+        "any_of" => Rule::AnyOf(args(tokens, word)?.iter().map(|b| match b.chars().collect::<Vec<_>>().as_slice() {
+            &[a, b] => Ok([a, b]),
+            _ => Err(anyhow!("{:?} isn't a bigram", b)),
+        }).collect::<Result<_>>()?),
+        "pair_twice" => Rule::PairTwice,
+        "all" => Rule::All(args(tokens, rule)?),
+        "any" => Rule::Any(args(tokens, rule)?),
+        "not" => match args(tokens, rule)?.as_slice() {
+            [r] => Rule::Not(Box::new(r.clone())),
+            _ => return Err(anyhow!("not takes just one rule")),
+        },
+        _ => return Err(anyhow!("no rule called {:?}", name)),
+    };
+    Ok(rule)
+}
+
+impl FromStr for Rule {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut tokens = tokens(s)?.into_iter().peekable();
+        let rule = rule(&mut tokens)?;
+        match tokens.next() {
+            None => Ok(rule),
+            Some(t) => Err(anyhow!("{:?} after the end of the rule", t)),
+        }
+    }
+}
+
+// how one leaf rule is getting on partway through a line
+enum Scan {
+    Count(usize),
+    // what the rule was looking for and where, once it's turned up
+    Found(Option<String>),
+    // where each pair first started too
+    Pairs(HashMap<(char, char), usize>, Option<String>),
+}
+
+// what a leaf rule came to, and why, either way
+struct Outcome {
+    held: bool,
+    why: String,
+}
+
+impl Rule {
+    // the leaves in the order check walks them
+    fn leaves<'a>(&'a self, out: &mut Vec<&'a Rule>) {
+        match self {
+            Rule::All(rules) | Rule::Any(rules) => rules.iter().for_each(|r| r.leaves(out)),
+            Rule::Not(rule) => rule.leaves(out),
+            _ => out.push(self),
+        }
+    }
+
+    // Runs every leaf over the line in a single pass, then works out what the
+    // whole thing comes to. On failure says which rule it was and why.
+    pub fn check(&self, line: &str) -> std::result::Result<(), String> {
+        let mut leaves = vec![];
+        self.leaves(&mut leaves);
+        let mut scans = leaves.iter().map(|leaf| match leaf {
+            Rule::AtLeast(..) => Scan::Count(0),
+            Rule::PairTwice => Scan::Pairs(HashMap::new(), None),
+            _ => Scan::Found(None),
+        }).collect::<Vec<_>>();
+
+        let chars = line.chars().collect::<Vec<_>>();
+        for (i, &c) in chars.iter().enumerate() {
+            for (leaf, scan) in leaves.iter().zip(&mut scans) {
+                match (leaf, scan) {
+                    (Rule::AtLeast(_, set), Scan::Count(n)) => *n += set.contains(&c) as usize,
+                    (Rule::Repeat(gap), Scan::Found(found @ None)) if i > *gap && chars[i - gap - 1] == c => {
+                        *found = Some(format!("{:?} at {} and {}", c, i - gap - 1, i));
+                    },
+                    (Rule::AnyOf(bigrams), Scan::Found(found @ None)) if i > 0 && bigrams.contains(&[chars[i - 1], c]) => {
+                        *found = Some(format!("\"{}{}\" at {}", chars[i - 1], c, i - 1));
+                    },
+                    (Rule::PairTwice, Scan::Pairs(first, found @ None)) if i > 0 => {
+                        let start = *first.entry((chars[i - 1], c)).or_insert(i - 1);
+                        if i > start + 2 {
+                            *found = Some(format!("\"{}{}\" at {} and {}", chars[i - 1], c, start, i - 1));
+                        }
+                    },
+                    _ => (),
+                }
+            }
+        }
+
+        let mut outcomes = leaves.iter().zip(scans).map(|(leaf, scan)| match (leaf, scan) {
+            (Rule::AtLeast(n, set), Scan::Count(count)) => Outcome {
+                held: count >= *n,
+                why: format!("{} of {}", count, set.iter().collect::<String>()),
+            },
+            (Rule::Repeat(gap), Scan::Found(found)) => Outcome {
+                held: found.is_some(),
+                why: found.unwrap_or_else(|| format!("nothing repeats {} apart", gap + 1)),
+            },
+            (Rule::AnyOf(_), Scan::Found(found)) => Outcome {
+                held: found.is_some(),
+                why: found.unwrap_or_else(|| "none of them".to_string()),
+            },
+            (Rule::PairTwice, Scan::Pairs(_, found)) => Outcome {
+                held: found.is_some(),
+                why: found.unwrap_or_else(|| "no pair shows up twice".to_string()),
+            },
+            _ => unreachable!(),
+        });
+        let (held, why) = self.judge(&mut outcomes);
+        if held {
+            Ok(())
+        } else {
+            Err(why)
+        }
+    }
+
+    // Takes this rule's leaf outcomes off the front, in leaves order, and says
+    // whether it held and why.
+    fn judge(&self, outcomes: &mut impl Iterator<Item = Outcome>) -> (bool, String) {
+        match self {
+            Rule::All(rules) => {
+                let judged = rules.iter().map(|r| r.judge(outcomes)).collect::<Vec<_>>();
+                match judged.into_iter().find(|(held, _)| !held) {
+                    Some(failed) => failed,
+                    None => (true, self.to_string()),
+                }
+            },
+            Rule::Any(rules) => {
+                let judged = rules.iter().map(|r| r.judge(outcomes)).collect::<Vec<_>>();
+                match judged.iter().find(|(held, _)| *held) {
+                    Some(held) => held.clone(),
+                    None => (false, judged.into_iter().map(|(_, why)| why).collect::<Vec<_>>().join("; ")),
+                }
+            },
+            Rule::Not(rule) => {
+                let (held, why) = rule.judge(outcomes);
+                (!held, format!("not {}", why))
+            },
+            _ => {
+                let outcome = outcomes.next().unwrap();
+                (outcome.held, format!("{}: {}", self, outcome.why))
+            },
+        }
+    }
+
+    pub fn nice(&self, line: &str) -> bool {
+        self.check(line).is_ok()
+    }
+}
+
+#[test]
+fn test_parse() {
+    let s = "all(at_least(3, aeiou), repeat(0), not(any_of(ab, cd, pq, xy)))";
+    let rule = s.parse::<Rule>().unwrap();
+    assert_eq!(rule, Rule::All(vec![
+        Rule::AtLeast(3, vec!['a', 'e', 'i', 'o', 'u']),
+        Rule::Repeat(0),
+        Rule::Not(Box::new(Rule::AnyOf(vec![['a', 'b'], ['c', 'd'], ['p', 'q'], ['x', 'y']]))),
+    ]));
+    assert_eq!(rule.to_string(), s);
+    assert_eq!("any( pair_twice,repeat(1) )".parse::<Rule>().unwrap().to_string(), "any(pair_twice, repeat(1))");
+
+    assert!("".parse::<Rule>().is_err());
+    assert!("repeat".parse::<Rule>().is_err());
+    assert!("repeat(x)".parse::<Rule>().is_err());
+    assert!("any_of(abc)".parse::<Rule>().is_err());
+    assert!("not(repeat(0), repeat(1))".parse::<Rule>().is_err());
+    assert!("all(repeat(0)".parse::<Rule>().is_err());
+    assert!("repeat(0) repeat(1)".parse::<Rule>().is_err());
+    assert!("sometimes(3)".parse::<Rule>().is_err());
+}
+
+#[test]
+fn test_check() {
+    let nice = "all(at_least(3, aeiou), repeat(0), not(any_of(ab, cd, pq, xy)))".parse::<Rule>().unwrap();
+    assert_eq!(nice.check("ugknbfddgicrmopn"), Ok(()));
+    assert_eq!(nice.check("aaa"), Ok(()));
+    assert_eq!(nice.check("jchzalrnumimnmhp"), Err("repeat(0): nothing repeats 1 apart".to_string()));
+    assert_eq!(nice.check("haegwjzuvuyypxyu"), Err("not any_of(ab, cd, pq, xy): \"xy\" at 13".to_string()));
+    assert_eq!(nice.check("dvszwmarrgswjxmb"), Err("at_least(3, aeiou): 1 of aeiou".to_string()));
+
+    let nice2 = "all(repeat(1), pair_twice)".parse::<Rule>().unwrap();
+    assert!(nice2.nice("qjhvhtzxzqqjkmpb"));
+    assert!(nice2.nice("xxyxx"));
+    assert_eq!(nice2.check("uurcxstgmygtbstg"), Err("repeat(1): nothing repeats 2 apart".to_string()));
+    assert_eq!(nice2.check("ieodomkazucvgmuy"), Err("pair_twice: no pair shows up twice".to_string()));
+    assert!(!nice2.nice("aaa"));
+    assert!(nice2.nice("aaaa"));
+
+    let either = "any(repeat(0), at_least(2, z))".parse::<Rule>().unwrap();
+    assert!(either.nice("abzcz"));
+    assert_eq!(either.check("abz"), Err("repeat(0): nothing repeats 1 apart; at_least(2, z): 1 of z".to_string()));
+    assert!("not(any(repeat(0), at_least(2, z)))".parse::<Rule>().unwrap().nice("abz"));
+}
@@ -0,0 +1,186 @@
+use anyhow::{anyhow, Result};
+
+use crate::{Change, Rule};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    // lights are just on or off
+    Binary,
+    // the Nordic elvish reading, every light has a brightness
+    Brightness,
+}
+
+impl Mode {
+    pub fn apply(self, change: &Change, light: u32) -> u32 {
+        use Change::*;
+        match (self, change) {
+            (Mode::Binary, On) => 1,
+            (Mode::Binary, Off) => 0,
+            (Mode::Binary, Toggle) => 1 - light,
+            (Mode::Brightness, On) => light + 1,
+            (Mode::Brightness, Off) => light.saturating_sub(1),
+            (Mode::Brightness, Toggle) => light + 2,
+        }
+    }
+}
+
+// A grid that only keeps a cell for each block of lights no rule edge runs
+// through, so a rule costs however many blocks it covers no matter how big the
+// grid is. All the rules have to be known up front to find the edges.
+pub struct Grid {
+    pub mode: Mode,
+    // where each block starts, plus the far edge of the grid at the end
+    xs: Vec<usize>,
+    ys: Vec<usize>,
+    cells: Vec<u32>,
+}
+
+impl Grid {
+    pub fn new(width: usize, height: usize, mode: Mode, rules: &[Rule]) -> Result<Grid> {
+        let mut xs = vec![0, width];
+        let mut ys = vec![0, height];
+        for (i, rule) in rules.iter().enumerate() {
+            if rule.to.0 >= width || rule.to.1 >= height || rule.from.0 >= width || rule.from.1 >= height {
+                return Err(anyhow!("rule {} ({:?}) goes outside the {}x{} grid", i + 1, rule, width, height));
+            }
+            xs.extend([rule.from.0, rule.to.0 + 1].iter().copied());
+            ys.extend([rule.from.1, rule.to.1 + 1].iter().copied());
+        }
+        xs.sort_unstable();
+        xs.dedup();
+        ys.sort_unstable();
+        ys.dedup();
+        let cells = vec![0; (xs.len() - 1) * (ys.len() - 1)];
+        Ok(Grid { mode, xs, ys, cells })
+    }
+
+    fn block(edges: &[usize], at: usize) -> usize {
+        edges.binary_search(&at).expect("rule the grid wasn't made with")
+    }
+
+    fn columns(&self) -> usize {
+        self.xs.len() - 1
+    }
+
+    pub fn apply(&mut self, rule: &Rule) {
+        if rule.from.0 > rule.to.0 || rule.from.1 > rule.to.1 {
+            return;
+        }
+        let (x0, x1) = (Grid::block(&self.xs, rule.from.0), Grid::block(&self.xs, rule.to.0 + 1));
+        let (y0, y1) = (Grid::block(&self.ys, rule.from.1), Grid::block(&self.ys, rule.to.1 + 1));
+        let columns = self.columns();
+        for y in y0..y1 {
+            for light in &mut self.cells[y * columns + x0..y * columns + x1] {
+                *light = self.mode.apply(&rule.change, *light);
+            }
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        *self.xs.last().unwrap()
+    }
+
+    pub fn height(&self) -> usize {
+        *self.ys.last().unwrap()
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> u32 {
+        let find = |edges: &[usize], at: usize| edges.partition_point(|&e| e <= at) - 1;
+        self.cells[find(&self.ys, y) * self.columns() + find(&self.xs, x)]
+    }
+
+    // each block's light and how many lights it stands for
+    fn blocks(&self) -> impl Iterator<Item = (u32, u64)> + '_ {
+        let columns = self.columns();
+        self.cells.iter().enumerate().map(move |(i, &light)| {
+            let (x, y) = (i % columns, i / columns);
+            let area = (self.xs[x + 1] - self.xs[x]) as u64 * (self.ys[y + 1] - self.ys[y]) as u64;
+            (light, area)
+        })
+    }
+
+    pub fn total(&self) -> u64 {
+        self.blocks().map(|(light, area)| light as u64 * area).sum()
+    }
+
+    pub fn brightest(&self) -> u32 {
+        self.cells.iter().copied().max().unwrap_or(0)
+    }
+
+    pub fn lit(&self) -> u64 {
+        self.blocks().filter(|&(light, _)| light > 0).map(|(_, area)| area).sum()
+    }
+}
+
+// every light one at a time, like it used to be done
+#[cfg(test)]
+fn dense(width: usize, height: usize, mode: Mode, rules: &[Rule]) -> Vec<Vec<u32>> {
+    let mut grid = vec![vec![0; width]; height];
+    for rule in rules {
+        for row in &mut grid[rule.from.1..=rule.to.1] {
+            for light in &mut row[rule.from.0..=rule.to.0] {
+                *light = mode.apply(&rule.change, *light);
+            }
+        }
+    }
+    grid
+}
+
+#[cfg(test)]
+fn parse(src: &str) -> Vec<Rule> {
+    src.lines().map(crate::parse_rule).collect::<Result<_>>().unwrap()
+}
+
+#[test]
+fn test_examples() {
+    let rules = parse("turn on 0,0 through 999,999\ntoggle 0,0 through 999,0\nturn off 499,499 through 500,500");
+    let mut grid = Grid::new(1000, 1000, Mode::Binary, &rules).unwrap();
+    for rule in &rules {
+        grid.apply(rule);
+    }
+    assert_eq!(grid.lit(), 1_000_000 - 1000 - 4);
+    assert_eq!((grid.get(5, 0), grid.get(5, 1), grid.get(499, 500)), (0, 1, 0));
+
+    let rules = parse("turn on 0,0 through 0,0\ntoggle 0,0 through 999,999");
+    let mut grid = Grid::new(1000, 1000, Mode::Brightness, &rules).unwrap();
+    for rule in &rules {
+        grid.apply(rule);
+    }
+    assert_eq!(grid.total(), 2_000_001);
+}
+
+#[test]
+fn test_against_dense() {
+    let rules = parse("turn on 3,4 through 20,9
+toggle 0,0 through 29,29
+turn off 10,10 through 12,25
+toggle 5,5 through 5,5
+turn on 28,0 through 29,1
+turn off 0,0 through 3,3
+toggle 7,2 through 21,17");
+    for mode in [Mode::Binary, Mode::Brightness].iter().copied() {
+        let dense = dense(30, 30, mode, &rules);
+        let mut grid = Grid::new(30, 30, mode, &rules).unwrap();
+        for rule in &rules {
+            grid.apply(rule);
+        }
+        for (y, row) in dense.iter().enumerate() {
+            for (x, &light) in row.iter().enumerate() {
+                assert_eq!(grid.get(x, y), light, "{:?} at {},{}", mode, x, y);
+            }
+        }
+        assert_eq!(grid.total(), dense.iter().flatten().map(|&l| l as u64).sum::<u64>());
+        assert_eq!(grid.lit(), dense.iter().flatten().filter(|&&l| l > 0).count() as u64);
+    }
+}
+
+#[test]
+fn test_huge() {
+    let rules = parse("turn on 0,0 through 999999,999999\ntoggle 1,1 through 999998,999998\nturn off 0,0 through 0,999999");
+    let mut grid = Grid::new(1_000_000, 1_000_000, Mode::Binary, &rules).unwrap();
+    for rule in &rules {
+        grid.apply(rule);
+    }
+    assert_eq!(grid.lit(), 3_000_000 - 4);
+    assert!(Grid::new(1000, 1000, Mode::Binary, &rules).is_err());
+}
@@ -0,0 +1,152 @@
+use std::{fs::File, io::{BufWriter, Write}, path::{Path, PathBuf}};
+
+use anyhow::{anyhow, Result};
+
+use crate::grid::{Grid, Mode};
+
+// 8 bit grayscale, one byte a pixel row by row
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    // Binary P5, which most viewers open and is trivial to diff byte for byte.
+    pub fn write_pgm(&self, out: &mut impl Write) -> Result<()> {
+        write!(out, "P5\n{} {}\n255\n", self.width, self.height)?;
+        out.write_all(&self.pixels)?;
+        Ok(())
+    }
+
+    pub fn write_png(&self, out: impl Write) -> Result<()> {
+        let mut encoder = png::Encoder::new(out, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&self.pixels)?;
+        Ok(())
+    }
+
+    // picks the format off the extension, before anything gets created
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let format = Format::of(path)?;
+        let mut out = BufWriter::new(File::create(path)?);
+        match format {
+            Format::Pgm => self.write_pgm(&mut out),
+            Format::Png => self.write_png(out),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Pgm,
+    Png,
+}
+
+impl Format {
+    pub fn of(path: &Path) -> Result<Format> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("pgm") => Ok(Format::Pgm),
+            Some("png") => Ok(Format::Png),
+            _ => Err(anyhow!("{} should end in .pgm or .png", path.display())),
+        }
+    }
+}
+
+impl Grid {
+    // The grid squashed down to fit in max by max pixels, if it has to be, by
+    // taking the light at the top left of the patch each pixel covers. Lit is
+    // white, and with brightness the brightest light is.
+    pub fn render(&self, max: usize) -> Image {
+        let step = self.width().div_ceil(max).max(self.height().div_ceil(max)).max(1);
+        let (width, height) = (self.width().div_ceil(step), self.height().div_ceil(step));
+
+        let brightest = match self.mode {
+            Mode::Binary => 1,
+            Mode::Brightness => self.brightest().max(1),
+        };
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            pixels.extend((0..width).map(|x| {
+                let light = self.get(x * step, y * step);
+                (light as u64 * 255 / brightest as u64) as u8
+            }));
+        }
+        Image { width, height, pixels }
+    }
+}
+
+// name-0001.png, name-0002.png... next to path, for frame n of an animation
+pub fn frame_path(path: &Path, n: usize) -> PathBuf {
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("frame");
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("png");
+    path.with_file_name(format!("{}-{:04}.{}", stem, n, ext))
+}
+
+#[cfg(test)]
+fn lights(size: usize, mode: Mode, src: &str) -> Grid {
+    let rules = src.lines().map(crate::parse_rule).collect::<Result<Vec<_>>>().unwrap();
+    let mut grid = Grid::new(size, size, mode, &rules).unwrap();
+    for rule in &rules {
+        grid.apply(rule);
+    }
+    grid
+}
+
+#[test]
+fn test_render() {
+    let grid = lights(4, Mode::Binary, "turn on 1,0 through 2,1\ntoggle 2,1 through 3,3");
+    let image = grid.render(1000);
+    assert_eq!((image.width, image.height), (4, 4));
+    assert_eq!(image.pixels, vec![
+        0, 255, 255, 0,
+        0, 255, 0, 255,
+        0, 0, 255, 255,
+        0, 0, 255, 255,
+    ]);
+
+    let mut pgm = vec![];
+    image.write_pgm(&mut pgm).unwrap();
+    assert!(pgm.starts_with(b"P5\n4 4\n255\n"));
+    assert_eq!(pgm.len(), 11 + 16);
+
+    // brightness scales to the brightest light, and big grids get squashed
+    let grid = lights(10, Mode::Brightness, "turn on 0,0 through 9,9\ntoggle 0,0 through 1,1");
+    let image = grid.render(5);
+    assert_eq!((image.width, image.height), (5, 5));
+    assert_eq!(&image.pixels[..3], &[255, 85, 85]);
+}
+
+#[test]
+fn test_png() {
+    let image = lights(1_000_000, Mode::Binary, "turn on 0,0 through 499999,999999").render(100);
+    assert_eq!((image.width, image.height), (100, 100));
+    let mut png = vec![];
+    image.write_png(&mut png).unwrap();
+    assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+
+    let decoder = png::Decoder::new(png.as_slice());
+    let mut reader = decoder.read_info().unwrap();
+    let mut buf = vec![0; reader.output_buffer_size()];
+    reader.next_frame(&mut buf).unwrap();
+    assert_eq!(buf, image.pixels);
+}
+
+#[test]
+fn test_frame_path() {
+    assert_eq!(frame_path(Path::new("out/lights.png"), 7), Path::new("out/lights-0007.png"));
+    assert_eq!(frame_path(Path::new("lights.pgm"), 123), Path::new("lights-0123.pgm"));
+}
+
+#[test]
+fn test_format() {
+    assert_eq!(Format::of(Path::new("out/lights.png")).unwrap(), Format::Png);
+    assert_eq!(Format::of(Path::new("lights.pgm")).unwrap(), Format::Pgm);
+    assert!(Format::of(Path::new("lights")).is_err());
+
+    let path = std::env::temp_dir().join(format!("day6-{}.jpg", std::process::id()));
+    let image = Image { width: 1, height: 1, pixels: vec![0] };
+    assert!(image.save(&path).is_err());
+    assert!(!path.exists());
+}
@@ -8,6 +8,16 @@ use nom::{
     sequence::{delimited, separated_pair, tuple},
 };
 
+use std::path::Path;
+
+mod grid;
+mod image;
+
+use grid::{Grid, Mode};
+
+// how many pixels across images get at most
+const IMAGE_SIZE: usize = 1000;
+
 #[derive(Clone, Debug)]
 enum Change {
     On,
@@ -45,9 +55,9 @@ fn parse_rule(s: &str) -> Result<Rule> {
                delimited(space1, parse_coord, space0)))(s);
     match parse {
         Ok((_, (change, from, _, to))) => Ok(Rule{
-            change: change,
-            from: from,
-            to: to,
+            change,
+            from,
+            to,
         }),
         Err(e) => Err(anyhow!("Error in rule '{}': {}", s, e)),
     }
@@ -60,41 +70,39 @@ fn parse_rules() -> Result<Vec<Rule>> {
         collect::<Result<Vec<_>, _>>()
 }
 
-type Grid = [[u32; 1000]; 1000];
+fn main() -> Result<()> {
+    let args = std::env::args().collect::<Vec<_>>();
+    let mode = match args.get(1).map_or("brightness", |m| m.as_str()) {
+        "binary" => Mode::Binary,
+        "brightness" => Mode::Brightness,
+        m => return Err(anyhow!("mode should be binary or brightness, not {}", m)),
+    };
+    let size = args.get(2).map_or(Ok(1000), |s| s.parse())?;
+    // a picture of the end result, and with every set a frame after every that many rules
+    let image = args.get(3).map(Path::new);
+    let every = args.get(4).map(|n| n.parse::<usize>()).transpose()?.filter(|&n| n > 0);
+    // a bad extension should fail now rather than after every rule has run
+    if let Some(path) = image {
+        image::Format::of(path)?;
+    }
 
-fn apply_rule(grid: &mut Grid, rule: &Rule) {
-    for y in rule.from.1..=rule.to.1 {
-        for x in rule.from.0..=rule.to.0 {
-            use Change::*;
-            match rule.change {
-                On => grid[y][x] += 1,
-                Off => {
-                    let l = &mut grid[y][x];
-                    if *l != 0 {
-                        *l -= 1;
-                    }
-                }
-                Toggle => grid[y][x] += 2
+    let rules = parse_rules()?;
+    let mut grid = Grid::new(size, size, mode, &rules)?;
+    for (i, r) in rules.iter().enumerate() {
+        grid.apply(r);
+        if let (Some(path), Some(every)) = (image, every) {
+            if (i + 1) % every == 0 || i + 1 == rules.len() {
+                grid.render(IMAGE_SIZE).save(&image::frame_path(path, i + 1))?;
             }
         }
-
     }
-}
-
-fn main() -> Result<()> {
-    let mut g = [[0; 1000]; 1000];
-    let rules = parse_rules()?;
-    for r in &rules {
-        apply_rule(&mut g, r);
+    if let Some(path) = image {
+        grid.render(IMAGE_SIZE).save(path)?;
     }
 
-    let mut count: u32 = 0;
-    for row in g.iter() {
-        for col in row.iter() {
-            count += *col
-        }
+    match mode {
+        Mode::Binary => println!("Lit: {}", grid.lit()),
+        Mode::Brightness => println!("Brightness: {}", grid.total()),
     }
-
-    println!("Brightness: {}", count);
     Ok(())
 }
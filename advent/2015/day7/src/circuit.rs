@@ -0,0 +1,188 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+
+use anyhow::{Result, anyhow};
+
+use crate::{Component, Wire, Signal};
+
+pub struct Circuit {
+    comps: Vec<Component>,
+    drivers: HashMap<Wire, usize>,
+    // every component reading a wire, once per input so it lines up with the in-degrees
+    fanout: HashMap<Wire, Vec<usize>>,
+    order: Vec<usize>,
+    rank: Vec<usize>,
+    overrides: HashMap<Wire, Signal>,
+}
+
+fn cycle_error(comps: &[Component], drivers: &HashMap<Wire, usize>, waiting: &[usize]) -> anyhow::Error {
+    // anything still waiting has an input driven by something else still waiting,
+    // so following those drivers backwards has to come round to a repeat
+    let mut cur = waiting.iter().position(|&w| w > 0).unwrap();
+    let mut path = Vec::new();
+    let mut seen = HashMap::new();
+    while !seen.contains_key(&cur) {
+        seen.insert(cur, path.len());
+        path.push(cur);
+        cur = comps[cur].wires().map(|w| drivers[w]).find(|&d| waiting[d] > 0).unwrap();
+    }
+    let mut wires = path[seen[&cur]..].iter().rev().map(|&i| comps[i].out.as_str()).collect::<Vec<_>>();
+    wires.push(wires[0]);
+    anyhow!("cycle through wires: {}", wires.join(" -> "))
+}
+
+impl Circuit {
+    pub fn new(comps: Vec<Component>) -> Result<Circuit> {
+        let mut drivers = HashMap::new();
+        for (i, comp) in comps.iter().enumerate() {
+            if drivers.insert(comp.out.clone(), i).is_some() {
+                return Err(anyhow!("wire {} has more than one driver", comp.out));
+            }
+        }
+
+        let mut fanout: HashMap<Wire, Vec<usize>> = HashMap::new();
+        let mut undriven = BTreeSet::new();
+        for (i, comp) in comps.iter().enumerate() {
+            for w in comp.wires() {
+                if !drivers.contains_key(w) {
+                    undriven.insert(w.as_str());
+                }
+                fanout.entry(w.clone()).or_default().push(i);
+            }
+        }
+        if !undriven.is_empty() {
+            return Err(anyhow!("undriven wires: {}", undriven.into_iter().collect::<Vec<_>>().join(", ")));
+        }
+
+        let mut waiting = comps.iter().map(|c| c.wires().count()).collect::<Vec<_>>();
+        let mut ready = (0..comps.len()).filter(|&i| waiting[i] == 0).collect::<VecDeque<_>>();
+        let mut order = Vec::with_capacity(comps.len());
+        while let Some(i) = ready.pop_front() {
+            order.push(i);
+            for &reader in fanout.get(&comps[i].out).into_iter().flatten() {
+                waiting[reader] -= 1;
+                if waiting[reader] == 0 {
+                    ready.push_back(reader);
+                }
+            }
+        }
+        if order.len() < comps.len() {
+            return Err(cycle_error(&comps, &drivers, &waiting));
+        }
+
+        let mut rank = vec![0; comps.len()];
+        for (r, &i) in order.iter().enumerate() {
+            rank[i] = r;
+        }
+
+        Ok(Circuit {
+            comps,
+            drivers,
+            fanout,
+            order,
+            rank,
+            overrides: HashMap::new(),
+        })
+    }
+
+    pub fn in_order(&self) -> impl Iterator<Item = &Component> {
+        self.order.iter().map(move |&i| &self.comps[i])
+    }
+
+    fn value(&self, i: usize, sigs: &BTreeMap<Wire, Signal>) -> Signal {
+        let comp = &self.comps[i];
+        match self.overrides.get(&comp.out) {
+            Some(&v) => v,
+            None => comp.eval(sigs).expect("inputs are evaluated first"),
+        }
+    }
+
+    pub fn eval(&self) -> BTreeMap<Wire, Signal> {
+        let mut sigs = BTreeMap::new();
+        for &i in &self.order {
+            let v = self.value(i, &sigs);
+            sigs.insert(self.comps[i].out.clone(), v);
+        }
+        sigs
+    }
+
+    fn mark_readers(&self, wire: &str, dirty: &mut BTreeSet<usize>) {
+        for &reader in self.fanout.get(wire).into_iter().flatten() {
+            dirty.insert(self.rank[reader]);
+        }
+    }
+
+    // Pins a wire to a value and re-runs only what's downstream of it, stopping
+    // wherever an output comes out unchanged. Returns how many components were re-run.
+    pub fn set(&mut self, sigs: &mut BTreeMap<Wire, Signal>, wire: &str, value: Signal) -> Result<usize> {
+        if !self.drivers.contains_key(wire) {
+            return Err(anyhow!("no wire {}", wire));
+        }
+        self.overrides.insert(wire.to_string(), value);
+        if sigs.insert(wire.to_string(), value) == Some(value) {
+            return Ok(0);
+        }
+
+        let mut dirty = BTreeSet::new();
+        self.mark_readers(wire, &mut dirty);
+        let mut runs = 0;
+        while let Some(r) = dirty.pop_first() {
+            let i = self.order[r];
+            runs += 1;
+            let v = self.value(i, sigs);
+            let out = &self.comps[i].out;
+            if sigs.insert(out.clone(), v) != Some(v) {
+                self.mark_readers(out, &mut dirty);
+            }
+        }
+        Ok(runs)
+    }
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = "123 -> x
+456 -> y
+x AND y -> d
+x OR y -> e
+x LSHIFT 2 -> f
+y RSHIFT 2 -> g
+NOT x -> h
+NOT y -> i";
+
+#[test]
+fn test_eval() {
+    let circuit = Circuit::new(crate::parse_components(EXAMPLE).unwrap()).unwrap();
+    let sigs = circuit.eval();
+    let expected = [("d", 72), ("e", 507), ("f", 492), ("g", 114), ("h", 65412), ("i", 65079), ("x", 123), ("y", 456)];
+    assert_eq!(sigs, expected.iter().map(|&(w, s)| (w.to_string(), s)).collect());
+
+    // order in the file doesn't matter
+    let circuit = Circuit::new(crate::parse_components("NOT b -> c\na -> b\n7 -> a").unwrap()).unwrap();
+    assert_eq!(circuit.eval()["c"], !7);
+}
+
+#[test]
+fn test_errors() {
+    let err = |s: &str| Circuit::new(crate::parse_components(s).unwrap()).err().unwrap().to_string();
+    assert_eq!(err("a AND q -> b\nz -> c\n1 -> a"), "undriven wires: q, z");
+    assert_eq!(err("1 -> a\na OR c -> b\nNOT b -> c\nb -> d"), "cycle through wires: c -> b -> c");
+    assert_eq!(err("a -> a"), "cycle through wires: a -> a");
+    assert_eq!(err("1 -> a\n2 -> a"), "wire a has more than one driver");
+}
+
+#[test]
+fn test_set() {
+    let mut circuit = Circuit::new(crate::parse_components(EXAMPLE).unwrap()).unwrap();
+    let mut sigs = circuit.eval();
+    assert_eq!(circuit.set(&mut sigs, "x", 123).unwrap(), 0);
+    assert_eq!(circuit.set(&mut sigs, "x", 0).unwrap(), 4);
+    assert_eq!(circuit.set(&mut sigs, "d", 5).unwrap(), 0);
+    assert_eq!(sigs, circuit.eval());
+    assert_eq!((sigs["d"], sigs["e"], sigs["h"]), (5, 456, 65535));
+    assert!(circuit.set(&mut sigs, "nope", 1).is_err());
+
+    // only the part of a chain that actually changes gets re-run
+    let mut circuit = Circuit::new(crate::parse_components("1 -> a\na AND 0 -> b\nNOT b -> c\nNOT a -> d").unwrap()).unwrap();
+    let mut sigs = circuit.eval();
+    assert_eq!(circuit.set(&mut sigs, "a", 2).unwrap(), 2);
+    assert_eq!(sigs["d"], !2);
+}
@@ -0,0 +1,131 @@
+use std::{collections::{BTreeMap, HashMap, HashSet}, fmt::Write};
+
+use anyhow::{Result, anyhow};
+use serde::{Serialize, Deserialize};
+
+use crate::{Component, Input, Wire, Signal};
+
+// everything feeding into wire, the wire included
+pub fn fan_in<'a>(comps: &'a [Component], wire: &str) -> Result<HashSet<&'a str>> {
+    let drivers = comps.iter().map(|c| (c.out.as_str(), c)).collect::<HashMap<_, _>>();
+    let root = drivers.get(wire).ok_or(anyhow!("no wire {}", wire))?;
+    let mut cone = HashSet::new();
+    let mut stack = vec![root.out.as_str()];
+    while let Some(w) = stack.pop() {
+        if !cone.insert(w) {
+            continue;
+        }
+        if let Some(comp) = drivers.get(w) {
+            stack.extend(comp.wires().map(|w| w.as_str()));
+        }
+    }
+    Ok(cone)
+}
+
+// Gates are nodes named after the wire they drive, constants get their own little
+// nodes. Pass signals to show resolved values and a cone to only draw part of it.
+pub fn to_dot(comps: &[Component], sigs: Option<&BTreeMap<Wire, Signal>>, cone: Option<&HashSet<&str>>) -> String {
+    let mut out = String::new();
+    writeln!(out, "digraph circuit {{").unwrap();
+    writeln!(out, "    node [shape=box];").unwrap();
+    for comp in comps {
+        if cone.is_some_and(|c| !c.contains(comp.out.as_str())) {
+            continue;
+        }
+        let value = match sigs.and_then(|s| s.get(&comp.out)) {
+            Some(v) => format!("{} = {}", comp.out, v),
+            None => comp.out.clone(),
+        };
+        writeln!(out, "    \"{}\" [label=\"{}\\n{}\"];", comp.out, comp.op, value).unwrap();
+        for (i, input) in comp.ins.iter().enumerate() {
+            match input {
+                Input::Connection(w) => writeln!(out, "    \"{}\" -> \"{}\";", w, comp.out).unwrap(),
+                Input::Constant(c) => {
+                    let id = format!("{}#{}", comp.out, i);
+                    writeln!(out, "    \"{}\" [label=\"{}\", shape=plaintext];", id, c).unwrap();
+                    writeln!(out, "    \"{}\" -> \"{}\";", id, comp.out).unwrap();
+                },
+            }
+        }
+    }
+    writeln!(out, "}}").unwrap();
+    out
+}
+
+// one entry per component, inputs are wire names or constants:
+// [{"op": "AND", "ins": ["x", 3], "out": "d"}, ...]
+#[derive(Serialize, Deserialize)]
+struct Gate {
+    op: String,
+    ins: Vec<Input>,
+    out: Wire,
+}
+
+pub fn to_netlist(comps: &[Component]) -> Result<String> {
+    let gates = comps.iter().map(|c| Gate {
+        op: c.op.to_string(),
+        ins: c.ins.clone(),
+        out: c.out.clone(),
+    }).collect::<Vec<_>>();
+    Ok(serde_json::to_string_pretty(&gates)?)
+}
+
+pub fn from_netlist(s: &str) -> Result<Vec<Component>> {
+    let gates = serde_json::from_str::<Vec<Gate>>(s)?;
+    gates.into_iter().map(|g| Component::new(g.op.parse()?, g.ins, g.out)).collect()
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = "123 -> x
+456 -> y
+x AND y -> d
+NOT x -> h
+d LSHIFT 2 -> f";
+
+#[test]
+fn test_fan_in() {
+    let comps = crate::parse_components(EXAMPLE).unwrap();
+    let cone = fan_in(&comps, "f").unwrap();
+    assert_eq!(cone, ["f", "d", "x", "y"].iter().copied().collect());
+    assert_eq!(fan_in(&comps, "h").unwrap(), ["h", "x"].iter().copied().collect());
+    assert!(fan_in(&comps, "q").is_err());
+}
+
+#[test]
+fn test_dot() {
+    let comps = crate::parse_components(EXAMPLE).unwrap();
+    let sigs = crate::circuit::Circuit::new(comps.clone()).unwrap().eval();
+    let cone = fan_in(&comps, "h").unwrap();
+    assert_eq!(to_dot(&comps, Some(&sigs), Some(&cone)), r#"digraph circuit {
+    node [shape=box];
+    "x" [label="WIRE\nx = 123"];
+    "x#0" [label="123", shape=plaintext];
+    "x#0" -> "x";
+    "h" [label="NOT\nh = 65412"];
+    "x" -> "h";
+}
+"#);
+    let dot = to_dot(&comps, None, None);
+    assert!(dot.contains("\"d\" [label=\"AND\\nd\"];"));
+    assert!(dot.contains("\"f#1\" [label=\"2\", shape=plaintext];"));
+}
+
+#[test]
+fn test_netlist() {
+    let comps = crate::parse_components(EXAMPLE).unwrap();
+    let json = to_netlist(&comps).unwrap();
+    assert!(json.contains(r#""ins": [
+      "x",
+      "y"
+    ]"#));
+    let back = from_netlist(&json).unwrap();
+    assert_eq!(back.len(), comps.len());
+    for (a, b) in comps.iter().zip(&back) {
+        assert_eq!((a.op, &a.ins, &a.out), (b.op, &b.ins, &b.out));
+    }
+
+    let comps = from_netlist(r#"[{"op": "OR", "ins": [1, 2], "out": "a"}, {"op": "NOT", "ins": ["a"], "out": "b"}]"#).unwrap();
+    assert_eq!(crate::circuit::Circuit::new(comps).unwrap().eval()["b"], !3);
+    assert!(from_netlist(r#"[{"op": "XOR", "ins": [1, 2], "out": "a"}]"#).is_err());
+    assert!(from_netlist(r#"[{"op": "NOT", "ins": [1, 2], "out": "a"}]"#).is_err());
+}
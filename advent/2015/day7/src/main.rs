@@ -1,14 +1,22 @@
-use anyhow::Result;
+mod circuit;
+mod export;
+mod symbolic;
+
+use anyhow::{Result, anyhow};
 use pest::{
     Parser,
     iterators::Pair};
 use pest_derive::Parser;
-use std::collections::{BTreeMap, HashSet};
+use serde::{Serialize, Deserialize};
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use circuit::Circuit;
 
 type Wire = String;
 type Signal = u16;
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
 enum Input {
     Constant(Signal),
     Connection(Wire)
@@ -18,23 +26,96 @@ impl Input {
     fn get(self: &Input, sigs: &BTreeMap<Wire, Signal>) -> Option<Signal> {
         match self {
             Input::Constant(c) => Some(*c),
-            Input::Connection(w) => sigs.get(w).map(Clone::clone),
+            Input::Connection(w) => sigs.get(w).copied(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Op {
+    Wire,
+    Not,
+    And,
+    Or,
+    Lshift,
+    Rshift,
+}
+
+impl Op {
+    fn arity(self) -> usize {
+        match self {
+            Op::Wire | Op::Not => 1,
+            _ => 2,
+        }
+    }
+
+    // shifting a u16 by 16 or more just empties it
+    fn apply(self, i: &[u16]) -> u16 {
+        match self {
+            Op::Wire => i[0],
+            Op::Not => !i[0],
+            Op::And => i[0] & i[1],
+            Op::Or => i[0] | i[1],
+            Op::Lshift => i[0].checked_shl(i[1] as u32).unwrap_or(0),
+            Op::Rshift => i[0].checked_shr(i[1] as u32).unwrap_or(0),
         }
     }
 }
 
+impl FromStr for Op {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Ok(match s {
+            "WIRE" => Op::Wire,
+            "NOT" => Op::Not,
+            "AND" => Op::And,
+            "OR" => Op::Or,
+            "LSHIFT" => Op::Lshift,
+            "RSHIFT" => Op::Rshift,
+            _ => return Err(anyhow!("bad op {}", s))
+        })
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Op::Wire => "WIRE",
+            Op::Not => "NOT",
+            Op::And => "AND",
+            Op::Or => "OR",
+            Op::Lshift => "LSHIFT",
+            Op::Rshift => "RSHIFT",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Clone)]
 struct Component {
     ins: Vec<Input>,
-    op: fn(&[u16]) -> u16,
+    op: Op,
     out: Wire,
 }
 
 impl Component {
-    fn run(&self, sigs: &mut BTreeMap<Wire, Signal>) {
-        let ins = self.ins.iter().map(|i| i.get(sigs)).collect::<Option<Vec<u16>>>();
-        if let Some(ins) = ins {
-            sigs.insert(self.out.clone(), (self.op)(&ins));
+    fn new(op: Op, ins: Vec<Input>, out: Wire) -> Result<Component> {
+        if ins.len() != op.arity() {
+            return Err(anyhow!("{} takes {} inputs, got {} for {}", op, op.arity(), ins.len(), out));
         }
+        Ok(Component { ins, op, out })
+    }
+
+    fn eval(&self, sigs: &BTreeMap<Wire, Signal>) -> Option<Signal> {
+        let ins = self.ins.iter().map(|i| i.get(sigs)).collect::<Option<Vec<u16>>>()?;
+        Some(self.op.apply(&ins))
+    }
+
+    fn wires(&self) -> impl Iterator<Item = &Wire> {
+        self.ins.iter().filter_map(|i| match i {
+            Input::Connection(w) => Some(w),
+            Input::Constant(_) => None,
+        })
     }
 }
 
@@ -51,44 +132,25 @@ fn parse_component(p: Pair<Rule>) -> Component {
     let mut inner = p.into_inner();
     let conn = inner.next().unwrap();
     let out = inner.next().unwrap().as_str().to_string();
-    match conn.as_rule() {
+    let component = match conn.as_rule() {
         Rule::binop => {
             let mut inner = conn.into_inner();
             let in1 = parse_input(inner.next().unwrap());
             let op = inner.next().unwrap();
             let in2 = parse_input(inner.next().unwrap());
-            let make = |op| Component {
-                ins: vec!(in1, in2),
-                op: op,
-                out: out,
-            };
-
-            match op.as_str() {
-                "AND" => make(|i| i[0] & i[1]),
-                "OR" => make(|i| i[0] | i[1]),
-                "RSHIFT" => make(|i| i[0] >> i[1]),
-                "LSHIFT" => make(|i| i[0] << i[1]),
-                _ => unreachable!(),
-            }
+            op.as_str().parse().and_then(|op| Component::new(op, vec!(in1, in2), out))
         },
         Rule::not => {
             let in1 = parse_input(conn.into_inner().next().unwrap());
-            Component {
-                ins: vec!(in1),
-                op: |i| !i[0],
-                out: out,
-            }
+            Component::new(Op::Not, vec!(in1), out)
         },
         Rule::input => {
             let in1 = parse_input(conn);
-            Component {
-                ins: vec!(in1),
-                op: |i| i[0],
-                out: out,
-            }
+            Component::new(Op::Wire, vec!(in1), out)
         },
         _ => unreachable!()
-    }
+    };
+    component.expect("grammar only allows known ops")
 }
 
 fn parse_components(i: &str) -> Result<Vec<Component>> {
@@ -103,55 +165,62 @@ fn parse_components(i: &str) -> Result<Vec<Component>> {
 #[grammar = "grammar.pest"]
 struct RuleParser;
 
-fn all_set(signals: &BTreeMap<Wire, Signal>, wires: &HashSet<Wire>) -> bool {
-    for w in wires {
-        if !signals.contains_key(w) {
-            return false;
-        }
-    }
-    true
-}
-
-fn run_circuit(comps: &Vec<Component>) -> BTreeMap<Wire, Signal> {
-    let mut signals = BTreeMap::new();
-    let mut wires = HashSet::new();
-
-    for comp in comps {
-        wires.insert(comp.out.clone());
-        for input in &comp.ins {
-            if let Input::Connection(w) = input {
-                wires.insert(w.clone());
+fn main() -> anyhow::Result<()> {
+    // "load <file>" first to use a netlist instead of the puzzle input, then
+    // optionally one of dot, expr or netlist with their own arguments
+    let args = std::env::args().collect::<Vec<_>>();
+    let (components, args) = match args.get(1).map(|a| a.as_str()) {
+        Some("load") => {
+            let path = args.get(2).ok_or(anyhow!("load needs a netlist file"))?;
+            (export::from_netlist(&std::fs::read_to_string(path)?)?, &args[3..])
+        },
+        _ => (parse_components(include_str!("input.txt"))?, &args[1..]),
+    };
+
+    match args.first().map(|a| a.as_str()) {
+        Some("dot") => {
+            // still draw circuits that don't evaluate, that's usually when it's needed
+            let signals = Circuit::new(components.clone()).map(|c| c.eval()).ok();
+            let cone = args.get(1).map(|w| export::fan_in(&components, w)).transpose()?;
+            print!("{}", export::to_dot(&components, signals.as_ref(), cone.as_ref()));
+            return Ok(());
+        },
+        Some("expr") => {
+            let wire = args.get(1).ok_or(anyhow!("expr needs a wire"))?;
+            // without a list of free wires, treat everything set straight from a constant as an input
+            let free = match args.get(2..).unwrap_or_default() {
+                [] => components.iter()
+                    .filter(|c| c.op == Op::Wire && matches!(c.ins[0], Input::Constant(_)))
+                    .map(|c| c.out.as_str())
+                    .collect(),
+                free => free.iter().map(|w| w.as_str()).collect(),
+            };
+            let circuit = Circuit::new(components.clone())?;
+            let exprs = symbolic::symbolic(&circuit, &free);
+            let expr = exprs.get(wire).ok_or(anyhow!("no wire {}", wire))?;
+            match expr.size() {
+                n if n <= 1000 => println!("{} = {}", wire, expr),
+                n => println!("{} is {} nodes, too big to print", wire, n),
             }
-        }
-    }
-
-    while !all_set(&signals, &wires) {
-        for comp in comps {
-            comp.run(&mut signals);
-        }
+            println!("depends on: {}", expr.vars().into_iter().collect::<Vec<_>>().join(" "));
+            return Ok(());
+        },
+        Some("netlist") => {
+            println!("{}", export::to_netlist(&components)?);
+            return Ok(());
+        },
+        _ => (),
     }
-
-    signals
-}
-
-fn main() -> anyhow::Result<()> {
-    let mut components = parse_components(include_str!("input.txt"))?;
     println!("{} components", components.len());
 
-    let signals = run_circuit(&components);
-    println!("a is {}", signals["a"]);
+    let mut circuit = Circuit::new(components)?;
+    let mut signals = circuit.eval();
+    let a = *signals.get("a").ok_or(anyhow!("no wire a to read the answer from"))?;
+    println!("a is {}", a);
 
     println!("patching circuit");
-    for comp in &mut components {
-        match (&comp.ins[..], comp.out.as_str()) {
-            ([Input::Constant(_)], "b") => {
-                comp.ins[0] = Input::Constant(signals["a"]);
-            },
-            _ => ()
-        }
-    }
-
-    let signals = run_circuit(&components);
+    let runs = circuit.set(&mut signals, "b", a)?;
+    println!("re-ran {} components", runs);
     println!("a is {}", signals["a"]);
 
     Ok(())
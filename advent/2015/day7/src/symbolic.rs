@@ -0,0 +1,202 @@
+use std::{collections::{BTreeSet, HashMap, HashSet}, fmt, rc::Rc};
+
+use crate::{Op, Input, Wire, Signal, circuit::Circuit};
+
+// Subexpressions are shared, so a wire read in a lot of places is only built once.
+// Printing one still expands every use of it.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Expr {
+    Const(Signal),
+    Var(Wire),
+    Gate(Op, Vec<Rc<Expr>>),
+}
+
+use Expr::*;
+
+fn same(a: &Rc<Expr>, b: &Rc<Expr>) -> bool {
+    Rc::ptr_eq(a, b) || match (&**a, &**b) {
+        (Const(x), Const(y)) => x == y,
+        (Var(x), Var(y)) => x == y,
+        _ => false,
+    }
+}
+
+fn konst(v: Signal) -> Rc<Expr> {
+    Rc::new(Const(v))
+}
+
+// builds op(args) and folds whatever can be folded on the way
+fn simplify(op: Op, args: Vec<Rc<Expr>>) -> Rc<Expr> {
+    let consts = args.iter().map(|a| match **a {
+        Const(c) => Some(c),
+        _ => None,
+    }).collect::<Option<Vec<_>>>();
+    if let Some(consts) = consts {
+        return konst(op.apply(&consts));
+    }
+
+    let arg = |i: usize| match *args[i] {
+        Const(c) => Some(c),
+        _ => None,
+    };
+    match (op, arg(0), args.get(1).and_then(|_| arg(1))) {
+        (Op::Wire, _, _) => return args[0].clone(),
+        (Op::Not, _, _) => if let Gate(Op::Not, inner) = &*args[0] {
+            return inner[0].clone();
+        },
+        (Op::And, Some(0), _) | (Op::And, _, Some(0)) => return konst(0),
+        (Op::And, Some(0xffff), _) | (Op::Or, Some(0), _) => return args[1].clone(),
+        (Op::And, _, Some(0xffff)) | (Op::Or, _, Some(0)) => return args[0].clone(),
+        (Op::Or, Some(0xffff), _) | (Op::Or, _, Some(0xffff)) => return konst(0xffff),
+        (Op::Lshift, Some(0), _) | (Op::Rshift, Some(0), _) => return konst(0),
+        (Op::Lshift, _, Some(0)) | (Op::Rshift, _, Some(0)) => return args[0].clone(),
+        (Op::Lshift, _, Some(n)) | (Op::Rshift, _, Some(n)) if n >= 16 => return konst(0),
+        // (x AND a) AND b is x AND (a AND b), same for OR
+        (Op::And, _, Some(b)) | (Op::Or, _, Some(b)) => if let Gate(inner_op, inner) = &*args[0] {
+            if *inner_op == op {
+                if let Const(a) = *inner[1] {
+                    return simplify(op, vec![inner[0].clone(), konst(op.apply(&[a, b]))]);
+                }
+            }
+        },
+        _ => (),
+    }
+    if matches!(op, Op::And | Op::Or) && same(&args[0], &args[1]) {
+        return args[0].clone();
+    }
+    // keep constants on the right so the rules above only need to look there
+    if matches!(op, Op::And | Op::Or) && arg(0).is_some() {
+        return simplify(op, vec![args[1].clone(), args[0].clone()]);
+    }
+    Rc::new(Gate(op, args))
+}
+
+// Every wire as an expression over the free wires, which are left as variables
+// no matter what drives them. Everything else folds down as far as it goes.
+pub fn symbolic(circuit: &Circuit, free: &HashSet<&str>) -> HashMap<Wire, Rc<Expr>> {
+    let mut exprs: HashMap<Wire, Rc<Expr>> = HashMap::new();
+    for comp in circuit.in_order() {
+        let expr = if free.contains(comp.out.as_str()) {
+            Rc::new(Var(comp.out.clone()))
+        } else {
+            let args = comp.ins.iter().map(|i| match i {
+                Input::Constant(c) => konst(*c),
+                Input::Connection(w) => exprs[w].clone(),
+            }).collect();
+            simplify(comp.op, args)
+        };
+        exprs.insert(comp.out.clone(), expr);
+    }
+    exprs
+}
+
+impl Expr {
+    // the free wires this actually depends on once everything is folded
+    pub fn vars(self: &Rc<Self>) -> BTreeSet<Wire> {
+        let mut seen = HashSet::new();
+        let mut vars = BTreeSet::new();
+        let mut stack = vec![self.clone()];
+        while let Some(e) = stack.pop() {
+            if !seen.insert(Rc::as_ptr(&e)) {
+                continue;
+            }
+            match &*e {
+                Const(_) => (),
+                Var(w) => {
+                    vars.insert(w.clone());
+                },
+                Gate(_, args) => stack.extend(args.iter().cloned()),
+            }
+        }
+        vars
+    }
+
+    // how many nodes printing this would take, saturating since that can get silly
+    pub fn size(self: &Rc<Self>) -> u64 {
+        fn go(e: &Rc<Expr>, memo: &mut HashMap<*const Expr, u64>) -> u64 {
+            if let Some(&n) = memo.get(&Rc::as_ptr(e)) {
+                return n;
+            }
+            let n = match &**e {
+                Gate(_, args) => args.iter().fold(1u64, |n, a| n.saturating_add(go(a, memo))),
+                _ => 1,
+            };
+            memo.insert(Rc::as_ptr(e), n);
+            n
+        }
+        go(self, &mut HashMap::new())
+    }
+}
+
+struct Nested<'a>(&'a Expr);
+
+impl fmt::Display for Nested<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Gate(_, args) if args.len() == 2 => write!(f, "({})", self.0),
+            e => write!(f, "{}", e),
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Const(c) => write!(f, "{}", c),
+            Var(w) => write!(f, "{}", w),
+            Gate(op, args) if args.len() == 1 => write!(f, "{} {}", op, Nested(&args[0])),
+            Gate(op, args) => write!(f, "{} {} {}", Nested(&args[0]), op, Nested(&args[1])),
+        }
+    }
+}
+
+#[cfg(test)]
+fn exprs(src: &str, free: &[&str]) -> HashMap<Wire, String> {
+    let circuit = Circuit::new(crate::parse_components(src).unwrap()).unwrap();
+    symbolic(&circuit, &free.iter().copied().collect()).into_iter().map(|(w, e)| (w, e.to_string())).collect()
+}
+
+#[test]
+fn test_fold() {
+    let e = exprs("123 -> x\n456 -> y\nx AND y -> d\nNOT x -> h", &[]);
+    assert_eq!((e["d"].as_str(), e["h"].as_str()), ("72", "65412"));
+
+    let e = exprs("1 -> x\n2 -> y
+x AND 65535 -> a
+65535 AND x -> b
+x OR 0 -> c
+x AND 0 -> d
+NOT x -> n
+NOT n -> nn
+x LSHIFT 0 -> s
+y RSHIFT 16 -> t
+x AND x -> u
+x AND 12 -> va
+va AND 10 -> vb
+3 OR x -> o
+x AND y -> xy
+xy OR 1 -> xyo
+xyo LSHIFT 2 -> z", &["x", "y"]);
+    let expected = [
+        ("a", "x"), ("b", "x"), ("c", "x"), ("d", "0"), ("n", "NOT x"), ("nn", "x"),
+        ("s", "x"), ("t", "0"), ("u", "x"), ("vb", "x AND 8"), ("o", "x OR 3"),
+        ("z", "((x AND y) OR 1) LSHIFT 2"),
+    ];
+    for (w, s) in expected {
+        assert_eq!(e[w], s, "{}", w);
+    }
+}
+
+#[test]
+fn test_vars() {
+    let circuit = Circuit::new(crate::parse_components("1 -> p\n2 -> q\n3 -> r
+p AND q -> pq
+r AND 0 -> rz
+pq OR rz -> a
+pq OR pq -> b").unwrap()).unwrap();
+    let exprs = symbolic(&circuit, &["p", "q", "r"].iter().copied().collect());
+    assert_eq!(exprs["a"].vars(), ["p", "q"].iter().map(|s| s.to_string()).collect());
+    assert_eq!(exprs["a"].to_string(), "p AND q");
+    assert_eq!(exprs["b"].size(), 3);
+    assert!(Rc::ptr_eq(&exprs["a"], &exprs["pq"]));
+}
@@ -0,0 +1,145 @@
+use std::fmt;
+
+// what went wrong decoding a literal, and the byte it went wrong at
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    // no " to open it, at the start
+    NoOpeningQuote,
+    // ran out before the closing ", at the end
+    Unterminated(usize),
+    // a \ right at the end with nothing after it
+    TrailingBackslash(usize),
+    // a \ followed by something that isn't \, " or x
+    BadEscape(usize, u8),
+    // \x without two hex digits after it
+    BadHex(usize),
+    // stuff after the closing "
+    Trailing(usize),
+}
+
+impl DecodeError {
+    pub fn offset(&self) -> usize {
+        match *self {
+            DecodeError::NoOpeningQuote => 0,
+            DecodeError::Unterminated(at)
+            | DecodeError::TrailingBackslash(at)
+            | DecodeError::BadEscape(at, _)
+            | DecodeError::BadHex(at)
+            | DecodeError::Trailing(at) => at,
+        }
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::NoOpeningQuote => write!(f, "no opening quote at byte 0"),
+            DecodeError::Unterminated(at) => write!(f, "no closing quote by byte {}", at),
+            DecodeError::TrailingBackslash(at) => write!(f, "backslash with nothing after it at byte {}", at),
+            DecodeError::BadEscape(at, b) => write!(f, "unknown escape \\{} at byte {}", b.escape_ascii(), at),
+            DecodeError::BadHex(at) => write!(f, "\\x needs two hex digits at byte {}", at),
+            DecodeError::Trailing(at) => write!(f, "more after the closing quote at byte {}", at),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+fn hex(b: u8) -> Option<u8> {
+    (b as char).to_digit(16).map(|d| d as u8)
+}
+
+// A quoted literal like "a\"b\x27" into the bytes it stands for.
+pub fn decode(lit: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    if lit.first() != Some(&b'"') {
+        return Err(DecodeError::NoOpeningQuote);
+    }
+    let mut bytes = vec![];
+    let mut i = 1;
+    loop {
+        match lit.get(i) {
+            None => return Err(DecodeError::Unterminated(lit.len())),
+            Some(b'"') => break,
+            Some(b'\\') => {
+                match lit.get(i + 1) {
+                    None => return Err(DecodeError::TrailingBackslash(i)),
+                    Some(&b @ (b'\\' | b'"')) => {
+                        bytes.push(b);
+                        i += 2;
+                    },
+                    Some(b'x') => {
+                        let digits = lit.get(i + 2).and_then(|&h| hex(h)).zip(lit.get(i + 3).and_then(|&l| hex(l)));
+                        let (h, l) = digits.ok_or(DecodeError::BadHex(i))?;
+                        bytes.push(h << 4 | l);
+                        i += 4;
+                    },
+                    Some(&b) => return Err(DecodeError::BadEscape(i, b)),
+                }
+            },
+            Some(&b) => {
+                bytes.push(b);
+                i += 1;
+            },
+        }
+    }
+    if i + 1 < lit.len() {
+        return Err(DecodeError::Trailing(i + 1));
+    }
+    Ok(bytes)
+}
+
+// Any bytes back into a quoted literal that decodes to them. Anything that
+// isn't printable ASCII goes in as \x.
+pub fn encode(bytes: &[u8]) -> Vec<u8> {
+    let mut lit = vec![b'"'];
+    for &b in bytes {
+        match b {
+            b'"' | b'\\' => lit.extend([b'\\', b]),
+            b' '..=b'~' => lit.push(b),
+            _ => lit.extend(format!("\\x{:02x}", b).bytes()),
+        }
+    }
+    lit.push(b'"');
+    lit
+}
+
+#[test]
+fn test_decode() {
+    assert_eq!(decode(br#""""#), Ok(vec![]));
+    assert_eq!(decode(br#""abc""#), Ok(b"abc".to_vec()));
+    assert_eq!(decode(br#""aaa\"aaa""#), Ok(b"aaa\"aaa".to_vec()));
+    assert_eq!(decode(br#""\x27""#), Ok(vec![0x27]));
+    assert_eq!(decode(br#""\xa8\\""#), Ok(vec![0xa8, b'\\']));
+}
+
+#[test]
+fn test_errors() {
+    assert_eq!(decode(b"abc"), Err(DecodeError::NoOpeningQuote));
+    assert_eq!(decode(br#""abc"#), Err(DecodeError::Unterminated(4)));
+    assert_eq!(decode(br#""ab\"#), Err(DecodeError::TrailingBackslash(3)));
+    assert_eq!(decode(br#""a\q""#), Err(DecodeError::BadEscape(2, b'q')));
+    assert_eq!(decode(br#""a\xg1""#), Err(DecodeError::BadHex(2)));
+    assert_eq!(decode(br#""a\x1""#), Err(DecodeError::BadHex(2)));
+    assert_eq!(decode(br#""a"b"#), Err(DecodeError::Trailing(3)));
+    assert_eq!(DecodeError::BadHex(2).to_string(), "\\x needs two hex digits at byte 2");
+    assert_eq!(DecodeError::BadEscape(2, b'q').offset(), 2);
+}
+
+#[test]
+fn test_encode() {
+    assert_eq!(encode(br#""""#), br#""\"\"""#.to_vec());
+    assert_eq!(encode(br#""aaa\"aaa""#), br#""\"aaa\\\"aaa\"""#.to_vec());
+    assert_eq!(encode(br#""\x27""#), br#""\"\\x27\"""#.to_vec());
+    assert_eq!(encode(&[0, 0xff, b'a']), br#""\x00\xffa""#.to_vec());
+}
+
+#[test]
+fn test_round_trip() {
+    let all = (0..=255).collect::<Vec<u8>>();
+    assert_eq!(decode(&encode(&all)), Ok(all));
+    for line in include_str!("input.txt").lines() {
+        let bytes = decode(line.as_bytes()).unwrap();
+        assert_eq!(decode(&encode(&bytes)), Ok(bytes));
+        assert_eq!(decode(&encode(line.as_bytes())), Ok(line.as_bytes().to_vec()));
+    }
+}
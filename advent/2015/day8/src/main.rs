@@ -1,44 +1,26 @@
+use anyhow::{anyhow, Result};
+
+mod literal;
+
+#[derive(Default)]
 struct Counts {
-    encoded: u32,
-    lit: u32,
-    mem: u32,
+    encoded: usize,
+    lit: usize,
+    mem: usize,
 }
 
-fn main() {
-    let mut counts = Counts{encoded: 0, lit: 0, mem: 0};
-    for line in include_str!("input.txt").lines() {
-        let mut iter = line.chars();
-        while let Some(c) = iter.next() {
-            counts.lit += 1;
-            match c {
-                '"' => {
-                    counts.encoded += 3; // "\" or \""
-                },
-                '\\' => {
-                    counts.mem += 1;
-                    counts.encoded += 2; // \\
-                    match iter.next() {
-                        Some('x') => {
-                            counts.encoded += 3; // xab
-                            counts.lit += 3;
-                            iter.next();
-                            iter.next();
-                        },
-                        Some(_) => {
-                            counts.encoded += 2; // \"
-                            counts.lit += 1;
-                        },
-                        None => panic!("bad input")
-                    }
-                },
-                _ => {
-                    counts.encoded += 1;
-                    counts.mem += 1;
-                }
-            }
-        }
+fn main() -> Result<()> {
+    let mut counts = Counts::default();
+    for (i, line) in include_str!("input.txt").lines().enumerate() {
+        // point at where it went wrong under the line itself
+        let mem = literal::decode(line.as_bytes())
+            .map_err(|e| anyhow!("line {}: {}\n{}\n{:>w$}", i + 1, e, line, "^", w = e.offset() + 1))?;
+        counts.lit += line.len();
+        counts.mem += mem.len();
+        counts.encoded += literal::encode(line.as_bytes()).len();
     }
 
     println!("{} - {} = {}", counts.lit, counts.mem, counts.lit - counts.mem);
     println!("{} - {} = {}", counts.encoded, counts.lit, counts.encoded - counts.lit);
+    Ok(())
 }
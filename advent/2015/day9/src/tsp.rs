@@ -0,0 +1,207 @@
+use std::collections::BTreeSet;
+
+use anyhow::{anyhow, Result};
+
+// no way to get there (yet)
+const NONE: u32 = u32::MAX;
+// The tables hold a u32 cost and a u8 parent for every set of cities and last
+// city in it, 2^n * n * 5 bytes. Keeping them under this comes to 22 cities at
+// about 460MB, where 23 would be about 965MB and 24 about 2GB.
+const TABLE_BYTES: usize = 512 << 20;
+const MAX_CITIES: usize = max_cities();
+
+const fn max_cities() -> usize {
+    let mut n = 1;
+    while (1usize << (n + 1)) * (n + 1) * 5 <= TABLE_BYTES {
+        n += 1;
+    }
+    n
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Goal {
+    Shortest,
+    Longest,
+}
+
+impl Goal {
+    fn better(self, a: u32, b: u32) -> bool {
+        b == NONE || match self {
+            Goal::Shortest => a < b,
+            Goal::Longest => a > b,
+        }
+    }
+}
+
+pub struct Map {
+    pub names: Vec<String>,
+    // None where there's no road between two places
+    dist: Vec<Vec<Option<u32>>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Route {
+    pub cities: Vec<usize>,
+    // legs[i] goes from cities[i] to the next one, round to the start if the tour is closed
+    pub legs: Vec<u32>,
+    pub total: u32,
+}
+
+impl Map {
+    pub fn new<'a>(roads: impl IntoIterator<Item = (&'a str, &'a str, u32)>) -> Map {
+        let roads = roads.into_iter().collect::<Vec<_>>();
+        let names = roads.iter().flat_map(|&(a, b, _)| [a, b]).collect::<BTreeSet<_>>();
+        let names = names.into_iter().map(str::to_string).collect::<Vec<_>>();
+        let index = |name: &str| names.iter().position(|n| n == name).unwrap();
+        let mut dist = vec![vec![None; names.len()]; names.len()];
+        for (a, b, d) in roads {
+            dist[index(a)][index(b)] = Some(d);
+            dist[index(b)][index(a)] = Some(d);
+        }
+        Map { names, dist }
+    }
+
+    pub fn dist(&self, a: usize, b: usize) -> Option<u32> {
+        self.dist[a][b]
+    }
+
+    // Held-Karp: dp[mask * n + last] is the best way through exactly the cities in
+    // mask finishing at last. An open route can start anywhere, a closed one always
+    // starts (and ends) at city 0 since where a loop starts makes no difference.
+    // Missing roads are just never taken, so None means there's no route at all.
+    pub fn route(&self, goal: Goal, closed: bool) -> Result<Option<Route>> {
+        let n = self.names.len();
+        if n > MAX_CITIES {
+            return Err(anyhow!("{} cities is more than the {} a route can be worked out for", n, MAX_CITIES));
+        }
+        if n == 0 {
+            return Ok(None);
+        }
+        let full = (1usize << n) - 1;
+        let mut dp = vec![NONE; (full + 1) * n];
+        let mut parent = vec![0u8; (full + 1) * n];
+        for start in 0..if closed { 1 } else { n } {
+            dp[(1 << start) * n + start] = 0;
+        }
+
+        for mask in 1..=full {
+            for last in (0..n).filter(|c| mask & 1 << c != 0) {
+                let here = dp[mask * n + last];
+                if here == NONE {
+                    continue;
+                }
+                for next in (0..n).filter(|c| mask & 1 << c == 0) {
+                    if let Some(d) = self.dist(last, next) {
+                        let at = (mask | 1 << next) * n + next;
+                        if goal.better(here + d, dp[at]) {
+                            dp[at] = here + d;
+                            parent[at] = last as u8;
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut end: Option<(u32, usize)> = None;
+        for last in 0..n {
+            let here = dp[full * n + last];
+            let back = if !closed || n == 1 { Some(0) } else { self.dist(last, 0) };
+            if let Some(back) = back.filter(|_| here != NONE) {
+                if end.is_none_or(|(best, _)| goal.better(here + back, best)) {
+                    end = Some((here + back, last));
+                }
+            }
+        }
+        let (total, mut last) = match end {
+            Some(end) => end,
+            None => return Ok(None),
+        };
+
+        let mut cities = vec![];
+        let mut mask = full;
+        loop {
+            cities.push(last);
+            let prev = parent[mask * n + last] as usize;
+            mask ^= 1 << last;
+            if mask == 0 {
+                break;
+            }
+            last = prev;
+        }
+        cities.reverse();
+
+        let mut legs = cities.windows(2).map(|w| self.dist(w[0], w[1]).unwrap()).collect::<Vec<_>>();
+        if closed && n > 1 {
+            legs.push(self.dist(cities[n - 1], cities[0]).unwrap());
+        }
+        Ok(Some(Route { cities, legs, total }))
+    }
+}
+
+#[cfg(test)]
+const EXAMPLE: [(&str, &str, u32); 3] = [("London", "Dublin", 464), ("London", "Belfast", 518), ("Dublin", "Belfast", 141)];
+
+#[test]
+fn test_example() {
+    let map = Map::new(EXAMPLE);
+    let shortest = map.route(Goal::Shortest, false).unwrap().unwrap();
+    assert_eq!(shortest.total, 605);
+    assert_eq!(shortest.legs.iter().sum::<u32>(), 605);
+    let names = shortest.cities.iter().map(|&c| map.names[c].as_str()).collect::<Vec<_>>();
+    assert!(names == ["London", "Dublin", "Belfast"] || names == ["Belfast", "Dublin", "London"], "{:?}", names);
+    assert_eq!(map.route(Goal::Longest, false).unwrap().unwrap().total, 982);
+    assert_eq!(map.route(Goal::Shortest, true).unwrap().unwrap().total, 464 + 518 + 141);
+    assert_eq!(map.route(Goal::Longest, true).unwrap().unwrap().legs.len(), 3);
+}
+
+#[test]
+fn test_missing_roads() {
+    // a line a - b - c - d, so there's only the one way through
+    let map = Map::new([("a", "b", 1), ("b", "c", 2), ("c", "d", 3)]);
+    for goal in [Goal::Shortest, Goal::Longest] {
+        let route = map.route(goal, false).unwrap().unwrap();
+        assert_eq!(route.total, 6);
+        assert!(route.cities == [0, 1, 2, 3] || route.cities == [3, 2, 1, 0]);
+        assert_eq!(map.route(goal, true).unwrap(), None);
+    }
+    // two islands
+    assert_eq!(Map::new([("a", "b", 1), ("c", "d", 1)]).route(Goal::Shortest, false).unwrap(), None);
+    assert_eq!(Map::new([("a", "b", 5)]).route(Goal::Shortest, true).unwrap().unwrap().total, 10);
+}
+
+#[test]
+fn test_against_brute_force() {
+    use itertools::Itertools;
+    let names = ["a", "b", "c", "d", "e", "f", "g"];
+    let mut roads = vec![];
+    for (i, a) in names.iter().enumerate() {
+        for (j, b) in names.iter().enumerate().skip(i + 1) {
+            // leave a few roads out
+            if (i * 5 + j * 3) % 7 != 0 {
+                roads.push((*a, *b, ((i * 31 + j * 17) % 50) as u32 + 1));
+            }
+        }
+    }
+    let map = Map::new(roads);
+    for closed in [false, true] {
+        let totals = (0..names.len()).permutations(names.len()).filter_map(|p| {
+            let back = if closed { Some(&p[0]) } else { None };
+            p.iter().chain(back).tuple_windows().map(|(&a, &b)| map.dist(a, b)).sum::<Option<u32>>()
+        }).collect::<Vec<_>>();
+        for (goal, want) in [(Goal::Shortest, totals.iter().min()), (Goal::Longest, totals.iter().max())] {
+            let route = map.route(goal, closed).unwrap().unwrap();
+            assert_eq!(Some(&route.total), want, "{:?} closed {}", goal, closed);
+            assert_eq!(route.legs.iter().sum::<u32>(), route.total);
+        }
+    }
+}
+
+#[test]
+fn test_too_many_cities() {
+    let names = (0..=MAX_CITIES).map(|i| format!("c{}", i)).collect::<Vec<_>>();
+    let roads = names.iter().zip(names.iter().skip(1)).map(|(a, b)| (a.as_str(), b.as_str(), 1));
+    let map = Map::new(roads);
+    assert_eq!(MAX_CITIES, 22);
+    assert_eq!(map.names.len(), MAX_CITIES + 1);
+    assert!(map.route(Goal::Shortest, false).is_err());
+}
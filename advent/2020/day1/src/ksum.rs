@@ -0,0 +1,280 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+
+// Every distinct way to pick k of the entries (each at most once) that add up
+// to target. Each solution comes back in ascending order, and solutions that use
+// the same values are only given once even if the entries differ.
+pub fn k_sum(values: &[i64], k: usize, target: i64) -> Result<Vec<Vec<i64>>> {
+    Ok(match k {
+        0 if target == 0 => vec![vec![]],
+        0 => vec![],
+        1 => values.iter().copied().filter(|&v| v == target).take(1).map(|v| vec![v]).collect(),
+        2 => two_sum(values, target),
+        3 => three_sum(values, target),
+        _ => return meet_in_middle(values, k, target),
+    })
+}
+
+// one pass to count each value, one over the distinct ones to find partners
+pub fn two_sum(values: &[i64], target: i64) -> Vec<Vec<i64>> {
+    let mut counts = HashMap::new();
+    for &v in values {
+        *counts.entry(v).or_insert(0) += 1;
+    }
+    let mut found = counts.iter()
+        .filter(|&(&v, &n)| {
+            let w = target - v;
+            v < w && counts.contains_key(&w) || v == w && n > 1
+        })
+        .map(|(&v, _)| vec![v, target - v])
+        .collect::<Vec<_>>();
+    found.sort_unstable();
+    found
+}
+
+// Sorted, then for each distinct smallest value the other two come in from
+// either end. O(n^2) with no extra space.
+pub fn three_sum(values: &[i64], target: i64) -> Vec<Vec<i64>> {
+    let mut sorted = values.to_vec();
+    sorted.sort_unstable();
+    let n = sorted.len();
+    let mut found = vec![];
+    for i in 0..n {
+        if i > 0 && sorted[i] == sorted[i - 1] {
+            continue;
+        }
+        let rest = target - sorted[i];
+        let (mut lo, mut hi) = (i + 1, n.saturating_sub(1));
+        while lo < hi {
+            let sum = sorted[lo] + sorted[hi];
+            if sum < rest {
+                lo += 1;
+            } else if sum > rest {
+                hi -= 1;
+            } else {
+                found.push(vec![sorted[i], sorted[lo], sorted[hi]]);
+                lo += 1;
+                while lo < hi && sorted[lo] == sorted[lo - 1] {
+                    lo += 1;
+                }
+                hi -= 1;
+            }
+        }
+    }
+    found
+}
+
+// Past this many low halves meet_in_middle gives up instead of eating all the
+// memory; each costs 16 bytes plus 4 a value.
+pub const MAX_HALVES: usize = 10_000_000;
+
+// What meet_in_middle works with: each distinct value once, with how many
+// times it turns up, smallest first. Picks are runs of indices into it that
+// never go down and never use a value more often than it's there.
+struct Distinct {
+    values: Vec<(i64, usize)>,
+    target: i64,
+}
+
+impl Distinct {
+    fn new(values: &[i64], target: i64) -> Distinct {
+        let mut sorted = values.to_vec();
+        sorted.sort_unstable();
+        let mut distinct: Vec<(i64, usize)> = vec![];
+        for v in sorted {
+            match distinct.last_mut() {
+                Some((last, n)) if *last == v => *n += 1,
+                _ => distinct.push((v, 1)),
+            }
+        }
+        Distinct { values: distinct, target }
+    }
+
+    // Could something that's picked sum so far, with left more still to come
+    // that are all at least lowest, still come out at the target?
+    fn feasible(&self, sum: i64, left: usize, lowest: i64) -> bool {
+        let highest = self.values.last().map_or(lowest, |&(v, _)| v);
+        let (sum, left, target) = (sum as i128, left as i128, self.target as i128);
+        sum + left * lowest as i128 <= target && target <= sum + left * highest as i128
+    }
+
+    // How many times the last index in picked is used, counting back from the end.
+    fn run(picked: &[u32]) -> usize {
+        picked.last().map_or(0, |&last| picked.iter().rev().take_while(|&&i| i == last).count())
+    }
+
+    // Every pick of size more values going on from picked, pruned by keep, each
+    // handed to f with its sum. Stops as soon as f says to.
+    fn picks(
+        &self, size: usize, picked: &mut Vec<u32>, sum: i64,
+        keep: &impl Fn(&[u32], i64) -> bool, f: &mut impl FnMut(&[u32], i64) -> bool,
+    ) -> bool {
+        if picked.len() == size {
+            return f(picked, sum);
+        }
+        let from = picked.last().map_or(0, |&i| i as usize);
+        for i in from..self.values.len() {
+            let (v, n) = self.values[i];
+            if picked.last() == Some(&(i as u32)) && Distinct::run(picked) >= n {
+                continue;
+            }
+            picked.push(i as u32);
+            let go_on = !keep(picked, sum + v) || self.picks(size, picked, sum + v, keep, f);
+            picked.pop();
+            if !go_on {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+// Splits each pick of k sorted values into its lowest k/2 and the rest. All
+// the low halves go in a table sorted by sum, then each high half looks up what
+// it's missing, keeping only low halves that end no later than it starts (with
+// enough of any value they share to go round) so every pick is seen once. Picks
+// work over distinct values with counts, and anything that can't reach the
+// target any more is dropped as soon as it's clear, so repeats and values too
+// big or small to matter cost nothing. Takes about d^(k/2) time and space for d
+// useful distinct values instead of n^k, and errors past MAX_HALVES.
+pub fn meet_in_middle(values: &[i64], k: usize, target: i64) -> Result<Vec<Vec<i64>>> {
+    if k < 2 {
+        return k_sum(values, k, target);
+    }
+    let d = Distinct::new(values, target);
+    let (low, high) = (k / 2, k - k / 2);
+    let value = |i: u32| d.values[i as usize].0;
+
+    // (sum, start in flat) for each low half, with the halves themselves end to end in flat
+    let mut halves = vec![];
+    let mut flat = vec![];
+    let keep_low = |picked: &[u32], sum: i64| d.feasible(sum, k - picked.len(), value(*picked.last().unwrap()));
+    d.picks(low, &mut vec![], 0, &keep_low, &mut |picked, sum| {
+        halves.push((sum, flat.len() as u32));
+        flat.extend_from_slice(picked);
+        halves.len() < MAX_HALVES
+    });
+    if halves.len() >= MAX_HALVES {
+        return Err(anyhow!("over {} ways to pick the low {} values, too many to meet in the middle", MAX_HALVES, low));
+    }
+    let half = |at: u32| &flat[at as usize..at as usize + low];
+    halves.sort_unstable_by_key(|&(sum, at)| (sum, half(at)[low - 1]));
+
+    // the high half has to leave the low one something between low of the
+    // smallest value and low of its own first value
+    let smallest = d.values.first().map_or(0, |&(v, _)| v) as i128;
+    let keep_high = |picked: &[u32], sum: i64| {
+        let (first, last) = (value(picked[0]) as i128, value(*picked.last().unwrap()));
+        let left = (high - picked.len()) as i128;
+        let highest = d.values.last().unwrap().0 as i128;
+        let (min, max) = (sum as i128 + left * last as i128, sum as i128 + left * highest);
+        let (need_min, need_max) = (target as i128 - low as i128 * first, target as i128 - low as i128 * smallest);
+        min <= need_max && need_min <= max
+    };
+    let mut found = vec![];
+    d.picks(high, &mut vec![], 0, &keep_high, &mut |picked, sum| {
+        let first = picked[0];
+        let leading = picked.iter().take_while(|&&i| i == first).count();
+        let start = halves.partition_point(|&(s, _)| s < target - sum);
+        for &(_, at) in halves[start..].iter().take_while(|&&(s, at)| s == target - sum && half(at)[low - 1] <= first) {
+            let lows = half(at);
+            if lows[low - 1] == first && Distinct::run(lows) + leading > d.values[first as usize].1 {
+                continue;
+            }
+            found.push(lows.iter().chain(picked).map(|&i| value(i)).collect::<Vec<_>>());
+        }
+        true
+    });
+    found.sort_unstable();
+    Ok(found)
+}
+
+// every pick checked, for the tests to check against
+#[cfg(test)]
+fn brute(values: &[i64], k: usize, target: i64) -> Vec<Vec<i64>> {
+    use itertools::Itertools;
+    let mut found = values.iter().copied().combinations(k)
+        .filter(|c| c.iter().sum::<i64>() == target)
+        .map(|mut c| {
+            c.sort_unstable();
+            c
+        })
+        .collect::<Vec<_>>();
+    found.sort_unstable();
+    found.dedup();
+    found
+}
+
+#[test]
+fn test_example() {
+    let report = [1721, 979, 366, 299, 675, 1456];
+    assert_eq!(k_sum(&report, 2, 2020).unwrap(), vec![vec![299, 1721]]);
+    assert_eq!(k_sum(&report, 3, 2020).unwrap(), vec![vec![366, 675, 979]]);
+    assert_eq!(k_sum(&report, 4, 2020).unwrap(), Vec::<Vec<i64>>::new());
+}
+
+#[test]
+fn test_against_brute() {
+    // plenty of repeats and negatives
+    let mut seed = 12345u64;
+    let values = (0..25).map(|_| {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (seed >> 33) as i64 % 30 - 10
+    }).collect::<Vec<_>>();
+    for k in 0..=5 {
+        for target in -20..40 {
+            let want = brute(&values, k, target);
+            assert_eq!(k_sum(&values, k, target).unwrap(), want, "k {} target {}", k, target);
+            if k >= 2 {
+                assert_eq!(meet_in_middle(&values, k, target).unwrap(), want, "k {} target {}", k, target);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_small() {
+    assert_eq!(k_sum(&[], 2, 0).unwrap(), Vec::<Vec<i64>>::new());
+    assert_eq!(k_sum(&[5], 2, 10).unwrap(), Vec::<Vec<i64>>::new());
+    assert_eq!(k_sum(&[5, 5], 2, 10).unwrap(), vec![vec![5, 5]]);
+    assert_eq!(k_sum(&[1, 2], 3, 3).unwrap(), Vec::<Vec<i64>>::new());
+    assert_eq!(meet_in_middle(&[1, 2], 3, 3).unwrap(), Vec::<Vec<i64>>::new());
+    assert_eq!(k_sum(&[3, 3, 3], 1, 3).unwrap(), vec![vec![3]]);
+}
+
+#[cfg(test)]
+fn ledger(n: usize, max: i64) -> Vec<i64> {
+    let mut seed = 987654321u64;
+    (0..n).map(|_| {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (seed >> 33) as i64 % max + 1
+    }).collect()
+}
+
+#[test]
+fn test_big_ledger() {
+    // 50k entries, most far too big to be in anything adding up to 2020
+    let values = ledger(50_000, 2_000_000);
+    let small = values.iter().copied().filter(|&v| v <= 2020).collect::<Vec<_>>();
+    assert!(small.len() > 30 && small.len() < 100, "{}", small.len());
+    for k in 4..=5 {
+        assert_eq!(meet_in_middle(&values, k, 2020).unwrap(), brute(&small, k, 2020), "k {}", k);
+    }
+
+    // lots of repeats of a few values
+    let values = ledger(50_000, 60);
+    let found = k_sum(&values, 6, 100).unwrap();
+    assert!(found.contains(&vec![1, 1, 1, 1, 36, 60]) && found.contains(&vec![15, 17, 17, 17, 17, 17]));
+    assert!(found.iter().all(|s| s.len() == 6 && s.iter().sum::<i64>() == 100 && s.windows(2).all(|w| w[0] <= w[1])));
+    let mut distinct = found.clone();
+    distinct.dedup();
+    assert_eq!(distinct.len(), found.len());
+}
+
+#[test]
+fn test_too_many_halves() {
+    // 50k different values that could all be in a sum to the middle
+    let values = (0..50_000).collect::<Vec<i64>>();
+    assert!(meet_in_middle(&values, 4, 100_000).is_err());
+}
@@ -1,12 +1,20 @@
-use itertools::Itertools;
+mod ksum;
 
 fn main() -> anyhow::Result<()> {
-    let input = std::fs::read_to_string("input.txt")?;
-    let rows = input.lines().map(|l| l.parse::<u32>()).collect::<Result<Vec<_>, _>>()?;
+    // how many entries, what they should add up to, and where to read them from
+    let args = std::env::args().collect::<Vec<_>>();
+    let k = args.get(1).map_or(Ok(3), |k| k.parse())?;
+    let target = args.get(2).map_or(Ok(2020), |t| t.parse())?;
+    let input = std::fs::read_to_string(args.get(3).map_or("input.txt", |p| p.as_str()))?;
+    let rows = input.lines().map(|l| l.trim().parse::<i64>()).collect::<Result<Vec<_>, _>>()?;
 
-    let answer = rows.into_iter().combinations(3).find(|n| n.iter().sum::<u32>() == 2020 ).expect("no match");
-
-    println!("answer {:?} {}", answer, answer.iter().product::<u32>());
+    let answers = ksum::k_sum(&rows, k, target)?;
+    if answers.is_empty() {
+        println!("no match");
+    }
+    for answer in answers {
+        println!("answer {:?} {}", answer, answer.iter().map(|&v| v as i128).product::<i128>());
+    }
 
     Ok(())
 }
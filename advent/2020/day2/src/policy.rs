@@ -0,0 +1,202 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    static ref RANGE: Regex = Regex::new(r"^(\d+)-(\d+) ([a-z])$").expect("bad regex");
+    static ref DISTINCT: Regex = Regex::new(r"^distinct (\d+)$").expect("bad regex");
+    static ref FORBID: Regex = Regex::new(r"^forbid (\S+(?:,\S+)*)$").expect("bad regex");
+}
+
+// What's before the ": " on a line of the database. Each policy reads some
+// of these and leaves the rest alone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rule {
+    // "1-3 a"
+    Range { lower: usize, upper: usize, letter: char },
+    // "distinct 5"
+    Distinct(usize),
+    // "forbid abc,xyz"
+    Forbid(Vec<String>),
+}
+
+impl FromStr for Rule {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        if let Some(captures) = RANGE.captures(s) {
+            Ok(Rule::Range {
+                lower: captures[1].parse()?,
+                upper: captures[2].parse()?,
+                letter: captures[3].chars().next().unwrap(), //wouldn't match if this was empty
+            })
+        } else if let Some(captures) = DISTINCT.captures(s) {
+            Ok(Rule::Distinct(captures[1].parse()?))
+        } else if let Some(captures) = FORBID.captures(s) {
+            Ok(Rule::Forbid(captures[1].split(',').map(str::to_string).collect()))
+        } else {
+            Err(anyhow!("no policy understands the rule {:?}", s))
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub rule: Rule,
+    pub pw: String,
+}
+
+pub fn parse(row: &str) -> Result<Entry> {
+    let (rule, pw) = row.split_once(": ").ok_or_else(|| anyhow!("bad row: {}", row))?;
+    Ok(Entry { rule: rule.trim().parse()?, pw: pw.to_string() })
+}
+
+pub trait Policy {
+    fn name(&self) -> &'static str;
+
+    // None if the rule is one of the other policies' kinds
+    fn allows(&self, rule: &Rule, pw: &str) -> Option<bool>;
+}
+
+// "1-3 a": between 1 and 3 a's, the sled rental place's policy
+pub struct Count;
+
+impl Policy for Count {
+    fn name(&self) -> &'static str {
+        "count"
+    }
+
+    fn allows(&self, rule: &Rule, pw: &str) -> Option<bool> {
+        let Rule::Range { lower, upper, letter } = *rule else { return None };
+        let n = pw.chars().filter(|&c| c == letter).count();
+        Some(lower <= n && n <= upper)
+    }
+}
+
+// "1-3 a": an a at exactly one of positions 1 and 3, counting from 1, the
+// toboggan place's policy. Both positions have to be in the password.
+pub struct Position;
+
+impl Policy for Position {
+    fn name(&self) -> &'static str {
+        "position"
+    }
+
+    fn allows(&self, rule: &Rule, pw: &str) -> Option<bool> {
+        let Rule::Range { lower, upper, letter } = *rule else { return None };
+        let at = |i: usize| i.checked_sub(1).and_then(|i| pw.chars().nth(i));
+        Some(match (at(lower), at(upper)) {
+            (Some(a), Some(b)) => (a == letter) ^ (b == letter),
+            _ => false,
+        })
+    }
+}
+
+// "distinct 5": at least 5 different chars
+pub struct MinDistinct;
+
+impl Policy for MinDistinct {
+    fn name(&self) -> &'static str {
+        "distinct"
+    }
+
+    fn allows(&self, rule: &Rule, pw: &str) -> Option<bool> {
+        let Rule::Distinct(min) = *rule else { return None };
+        let mut chars = pw.chars().collect::<Vec<_>>();
+        chars.sort_unstable();
+        chars.dedup();
+        Some(chars.len() >= min)
+    }
+}
+
+// "forbid abc,xyz": neither abc nor xyz anywhere in it
+pub struct Forbid;
+
+impl Policy for Forbid {
+    fn name(&self) -> &'static str {
+        "forbid"
+    }
+
+    fn allows(&self, rule: &Rule, pw: &str) -> Option<bool> {
+        let Rule::Forbid(subs) = rule else { return None };
+        Some(!subs.iter().any(|s| pw.contains(s.as_str())))
+    }
+}
+
+pub fn all() -> Vec<Box<dyn Policy>> {
+    vec![Box::new(Count), Box::new(Position), Box::new(MinDistinct), Box::new(Forbid)]
+}
+
+// how one policy did over the whole database
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tally {
+    pub policy: &'static str,
+    pub pass: usize,
+    pub fail: usize,
+    // rules written for the other policies
+    pub skipped: usize,
+}
+
+pub fn tally(policies: &[Box<dyn Policy>], entries: &[Entry]) -> Vec<Tally> {
+    policies.iter().map(|p| {
+        let mut tally = Tally { policy: p.name(), pass: 0, fail: 0, skipped: 0 };
+        for e in entries {
+            match p.allows(&e.rule, &e.pw) {
+                Some(true) => tally.pass += 1,
+                Some(false) => tally.fail += 1,
+                None => tally.skipped += 1,
+            }
+        }
+        tally
+    }).collect()
+}
+
+#[test]
+fn test_example() {
+    let entries = ["1-3 a: abcde", "1-3 b: cdefg", "2-9 c: ccccccccc"].iter().map(|r| parse(r)).collect::<Result<Vec<_>>>().unwrap();
+    let count = entries.iter().map(|e| Count.allows(&e.rule, &e.pw)).collect::<Vec<_>>();
+    assert_eq!(count, vec![Some(true), Some(false), Some(true)]);
+    let position = entries.iter().map(|e| Position.allows(&e.rule, &e.pw)).collect::<Vec<_>>();
+    assert_eq!(position, vec![Some(true), Some(false), Some(false)]);
+}
+
+#[cfg(test)]
+fn rule(s: &str) -> Rule {
+    s.parse().unwrap()
+}
+
+#[test]
+fn test_policies() {
+    assert_eq!(MinDistinct.allows(&rule("distinct 4"), "abcab"), Some(false));
+    assert_eq!(MinDistinct.allows(&rule("distinct 3"), "abcab"), Some(true));
+    assert_eq!(Forbid.allows(&rule("forbid ab,xyz"), "cabbage"), Some(false));
+    assert_eq!(Forbid.allows(&rule("forbid ab,xyz"), "xylophone"), Some(true));
+    assert_eq!(Count.allows(&rule("distinct 3"), "abc"), None);
+    assert_eq!(MinDistinct.allows(&rule("1-3 a"), "abc"), None);
+    assert_eq!(Count.allows(&rule("0-2 a"), "bcd"), Some(true));
+    // both positions have to be there
+    assert_eq!(Position.allows(&rule("2-9 a"), "ba"), Some(false));
+    assert_eq!(Position.allows(&rule("0-2 a"), "ba"), Some(false));
+    assert_eq!(Position.allows(&rule("1-2 a"), "ba"), Some(true));
+}
+
+#[test]
+fn test_bad_rules() {
+    assert!("forbid ".parse::<Rule>().is_err());
+    assert!("1-3 ab".parse::<Rule>().is_err());
+    assert!("distinct -1".parse::<Rule>().is_err());
+    assert!("99999999999999999999-1 a".parse::<Rule>().is_err());
+    assert_eq!(parse("at least 3: abc").unwrap_err().to_string(), "no policy understands the rule \"at least 3\"");
+}
+
+#[test]
+fn test_tally() {
+    let db = "1-3 a: abcde\n1-3 b: cdefg\n2-9 c: ccccccccc\ndistinct 3: aab\nforbid ab: cab\nforbid zz,yy: pizza: yes";
+    let entries = db.lines().map(parse).collect::<Result<Vec<_>>>().unwrap();
+    assert_eq!(entries[5], Entry { rule: Rule::Forbid(vec!["zz".to_string(), "yy".to_string()]), pw: "pizza: yes".to_string() });
+    let tallies = tally(&all(), &entries);
+    let counts = tallies.iter().map(|t| (t.policy, t.pass, t.fail, t.skipped)).collect::<Vec<_>>();
+    assert_eq!(counts, vec![("count", 2, 1, 3), ("position", 1, 2, 3), ("distinct", 0, 1, 5), ("forbid", 0, 2, 4)]);
+    assert!(parse("1-3 a abcde").is_err());
+}
@@ -1,60 +1,44 @@
-#[derive(Debug)]
-struct Map {
-    trees: Vec<u32>,
-}
+use std::{ops::RangeInclusive, str::FromStr};
 
-impl Map {
-    fn new() -> Map {
-        Map {
-            trees: Vec::new()
-        }
-    }
+use anyhow::anyhow;
 
-    fn count_trees(&self, x: usize, y: usize) -> usize {
-        let mut trees = 0;
-        let mut xpos: usize = 0;
-        for row in self.trees.iter().step_by(y) {
-            let shift = 31 - xpos;
-            //println!("r: {:#034b} x: {} y: {}\nm: {:#034b} shift: {} match: {}\n", row, xpos, ypos, 1 << shift, shift, row & (1 << shift));
-            if (row & (1 << shift)) != 0 {
-                trees += 1;
-            }
-
-            xpos = (xpos + x) % 31; //each row is actually 31 wide, not 32
-        }
+mod map;
 
-        trees
-    }
+use map::{Goal, Map};
 
-    fn add_row(&mut self, line: &str) {
-        let mut row: u32 = 0;
-        let mut bit: u32 = 1 << 31;
-        for c in line.chars() {
-            if c == '#' {
-                row |= bit;
-            }
-            bit >>= 1;
-        }
-        self.trees.push(row);
-    }
+// "lo..hi", both ends included
+fn bounds<T: FromStr>(s: &str) -> anyhow::Result<RangeInclusive<T>> {
+    let bad = || anyhow!("bounds should look like -3..7 not {:?}", s);
+    let (lo, hi) = s.split_once("..").ok_or_else(bad)?;
+    Ok(lo.parse().map_err(|_| bad())?..=hi.parse().map_err(|_| bad())?)
 }
 
 fn main() -> anyhow::Result<()> {
+    // the dx and dy bounds to search, and "trace" to see each row the puzzle slopes land on
+    let args = std::env::args().collect::<Vec<_>>();
+    let trace = args.iter().any(|a| a == "trace");
+    let mut args = args.iter().skip(1).filter(|a| *a != "trace");
+    let dxs = args.next().map_or(Ok(-10..=10), |a| bounds(a))?;
+    let dys = args.next().map_or(Ok(1..=5), |a| bounds(a))?;
+
     let input = std::fs::read_to_string("input.txt")?;
-    let mut map = Map::new();
-    for line in input.lines() {
-        map.add_row(line);
-    }
-    let slopes: Vec<(usize, usize)> = vec![
+    let map = Map::parse(&input)?;
+    let slopes: Vec<(i64, usize)> = vec![
         (1, 1),
         (3, 1),
         (5, 1),
         (7, 1),
         (1, 2),
     ];
-    let answer = slopes.into_iter().map(|(x, y)| map.count_trees(x, y)).product::<usize>();
+    let answer = slopes.into_iter().map(|(x, y)| map.count_trees(x, y, trace)).product::<usize>();
 
     println!("answer {}", answer);
 
+    for goal in [Goal::Fewest, Goal::Most].iter().copied() {
+        if let Some(((dx, dy), trees)) = map.search(dxs.clone(), dys.clone(), goal) {
+            println!("{:?} trees between dx {:?} and dy {:?}: {} going ({}, {})", goal, dxs, dys, trees, dx, dy);
+        }
+    }
+
     Ok(())
 }
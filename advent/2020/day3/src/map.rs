@@ -0,0 +1,145 @@
+use std::ops::RangeInclusive;
+
+use anyhow::{anyhow, Result};
+
+// Trees as one bit per square, each row padded out to a whole number of words.
+// The map repeats forever to the left and right.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Map {
+    pub width: usize,
+    pub height: usize,
+    words: usize,
+    trees: Vec<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Goal {
+    Fewest,
+    Most,
+}
+
+impl Map {
+    pub fn parse(src: &str) -> Result<Map> {
+        let width = src.lines().next().map_or(0, |l| l.len());
+        let words = width.div_ceil(64);
+        let mut trees = vec![];
+        let mut height = 0;
+        for (y, line) in src.lines().enumerate() {
+            if line.len() != width {
+                return Err(anyhow!("row {} is {} wide, not {}", y + 1, line.len(), width));
+            }
+            let mut row = vec![0u64; words];
+            for (x, c) in line.chars().enumerate() {
+                match c {
+                    '#' => row[x / 64] |= 1 << (x % 64),
+                    '.' => (),
+                    _ => return Err(anyhow!("bad square {:?} at {},{}", c, x, y)),
+                }
+            }
+            trees.extend(row);
+            height += 1;
+        }
+        Ok(Map { width, height, words, trees })
+    }
+
+    // x wraps around, so it can be anything
+    pub fn is_tree(&self, x: i64, y: usize) -> bool {
+        let x = x.rem_euclid(self.width as i64) as usize;
+        self.trees[y * self.words + x / 64] >> (x % 64) & 1 == 1
+    }
+
+    // Starts at the top left and goes dx across and dy down until it's past
+    // the bottom. With trace on it prints each row it lands on with an X where it
+    // hit a tree and an O where it didn't.
+    pub fn count_trees(&self, dx: i64, dy: usize, trace: bool) -> usize {
+        assert!(dy > 0, "never gets to the bottom going 0 down");
+        if self.width == 0 {
+            return 0;
+        }
+        let mut trees = 0;
+        for (step, y) in (0..self.height).step_by(dy).enumerate() {
+            let x = step as i64 * dx;
+            let tree = self.is_tree(x, y);
+            trees += tree as usize;
+            if trace {
+                let at = x.rem_euclid(self.width as i64) as usize;
+                let row = (0..self.width).map(|i| match (i == at, self.is_tree(i as i64, y)) {
+                    (true, true) => 'X',
+                    (true, false) => 'O',
+                    (false, true) => '#',
+                    (false, false) => '.',
+                }).collect::<String>();
+                println!("{} x: {} y: {} trees: {}", row, x, y, trees);
+            }
+        }
+        trees
+    }
+
+    // The slope within the bounds that hits the fewest or most trees, and how
+    // many. Ties go to the first found, smallest dy then smallest dx.
+    pub fn search(&self, dxs: RangeInclusive<i64>, dys: RangeInclusive<usize>, goal: Goal) -> Option<((i64, usize), usize)> {
+        let slopes = dys.filter(|&dy| dy > 0).flat_map(|dy| dxs.clone().map(move |dx| (dx, dy)));
+        let mut best: Option<((i64, usize), usize)> = None;
+        for (dx, dy) in slopes {
+            let trees = self.count_trees(dx, dy, false);
+            let better = match (best, goal) {
+                (None, _) => true,
+                (Some((_, b)), Goal::Fewest) => trees < b,
+                (Some((_, b)), Goal::Most) => trees > b,
+            };
+            if better {
+                best = Some(((dx, dy), trees));
+            }
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = "..##.......
+#...#...#..
+.#....#..#.
+..#.#...#.#
+.#...##..#.
+..#.##.....
+.#.#.#....#
+.#........#
+#.##...#...
+#...##....#
+.#..#...#.#";
+
+#[test]
+fn test_example() {
+    let map = Map::parse(EXAMPLE).unwrap();
+    assert_eq!((map.width, map.height), (11, 11));
+    let counts = [(1, 1), (3, 1), (5, 1), (7, 1), (1, 2)].iter().map(|&(dx, dy)| map.count_trees(dx, dy, false)).collect::<Vec<_>>();
+    assert_eq!(counts, vec![2, 7, 3, 4, 2]);
+    // going left is going right the rest of the way round
+    assert_eq!(map.count_trees(-8, 1, false), 7);
+    assert_eq!(map.count_trees(14, 1, false), 7);
+}
+
+#[test]
+fn test_wide() {
+    // trees at 0 and 99 only, so going a whole width either way hits every row
+    let row = format!("#{}#", ".".repeat(98));
+    let map = Map::parse(&vec![row; 5].join("\n")).unwrap();
+    assert_eq!(map.width, 100);
+    assert!(map.is_tree(99, 3) && map.is_tree(-1, 3) && map.is_tree(200, 0) && !map.is_tree(64, 1));
+    assert_eq!(map.count_trees(100, 1, false), 5);
+    assert_eq!(map.count_trees(-100, 1, false), 5);
+    assert_eq!(map.count_trees(99, 1, false), 2);
+    assert_eq!(map.count_trees(-1, 1, false), 2);
+    assert_eq!(map.count_trees(1, 1, false), 1);
+    assert_eq!(map.count_trees(0, 2, false), 3);
+}
+
+#[test]
+fn test_search() {
+    let map = Map::parse(EXAMPLE).unwrap();
+    assert_eq!(map.search(1..=7, 1..=1, Goal::Most), Some(((3, 1), 7)));
+    assert_eq!(map.search(-3..=3, 1..=2, Goal::Fewest), Some(((2, 1), 1)));
+    assert_eq!(map.search(1..=3, 0..=0, Goal::Most), None);
+    assert!(Map::parse("..#\n.#").is_err());
+    assert!(Map::parse("..#\n.#o").is_err());
+}
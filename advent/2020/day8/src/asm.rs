@@ -0,0 +1,137 @@
+use std::{collections::{HashMap, BTreeSet}, fmt::Write};
+
+use anyhow::{Result, Context, anyhow};
+
+use crate::vm::{Instr, Op};
+
+fn is_label(s: &str) -> bool {
+    let mut chars = s.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn jump_target(pc: usize, instr: &Instr, len: usize) -> Option<usize> {
+    pc.checked_add_signed(instr.arg as isize).filter(|&t| t <= len)
+}
+
+// Plain puzzle input is valid source too. On top of that:
+//   # or ; starts a comment
+//   name: labels the next instruction, or the end of the program if there isn't one
+//   jmp/jz/jnz/nop take a label in place of the relative offset
+pub fn assemble(src: &str) -> Result<Vec<Instr>> {
+    let mut labels = HashMap::new();
+    let mut lines = Vec::new();
+    for (i, line) in src.lines().enumerate() {
+        let mut line = line.split(&['#', ';'][..]).next().unwrap_or("").trim();
+        while let Some((label, rest)) = line.split_once(':') {
+            let label = label.trim();
+            if !is_label(label) {
+                return Err(anyhow!("line {}: bad label {:?}", i + 1, label));
+            }
+            if labels.insert(label, lines.len()).is_some() {
+                return Err(anyhow!("line {}: duplicate label {}", i + 1, label));
+            }
+            line = rest.trim();
+        }
+        if !line.is_empty() {
+            lines.push((i + 1, line));
+        }
+    }
+
+    lines.iter().enumerate().map(|(pc, &(lineno, text))| {
+        let words = text.split_whitespace().collect::<Vec<_>>();
+        let (last, rest) = words.split_last().unwrap();
+        if rest.is_empty() || !is_label(last) {
+            return text.parse::<Instr>().with_context(|| format!("line {}: {}", lineno, text));
+        }
+        let target = labels.get(last).ok_or(anyhow!("line {}: undefined label {}", lineno, last))?;
+        let offset = *target as i64 - pc as i64;
+        let instr = format!("{} {:+}", rest.join(" "), offset).parse::<Instr>()
+            .with_context(|| format!("line {}: {}", lineno, text))?;
+        if !instr.is_jump() && instr.op != Op::Nop {
+            return Err(anyhow!("line {}: {} can't take a label", lineno, instr.op));
+        }
+        Ok(instr)
+    }).collect()
+}
+
+// Every in-range jump target gets an L<pc> label. nop args are left as offsets since
+// they usually aren't meant as targets, out of range jumps are left alone too.
+pub fn disassemble(prog: &[Instr]) -> String {
+    let len = prog.len();
+    let targets = prog.iter().enumerate()
+        .filter(|(_, instr)| instr.is_jump())
+        .filter_map(|(pc, instr)| jump_target(pc, instr, len))
+        .collect::<BTreeSet<_>>();
+
+    let mut out = String::new();
+    for (pc, instr) in prog.iter().enumerate() {
+        if targets.contains(&pc) {
+            writeln!(out, "L{}:", pc).unwrap();
+        }
+        match jump_target(pc, instr, len) {
+            Some(target) if instr.is_jump() => writeln!(out, "    {} L{}", instr.op, target).unwrap(),
+            _ => writeln!(out, "    {}", instr).unwrap(),
+        }
+    }
+    if targets.contains(&len) {
+        writeln!(out, "L{}:", len).unwrap();
+    }
+    out
+}
+
+#[test]
+fn test_assemble() {
+    let src = "
+# count b down from 3
+        acc b +3
+loop:   acc +2       ; a += 2
+        acc b -1
+        jnz b loop
+        jz a end
+        acc +100
+end:
+";
+    let prog = assemble(src).unwrap();
+    let plain = ["acc b +3", "acc +2", "acc b -1", "jnz b -2", "jz a +2", "acc +100"]
+        .iter().map(|l| l.parse()).collect::<Result<Vec<Instr>>>().unwrap();
+    assert_eq!(prog, plain);
+
+    assert_eq!(assemble("nop +0\nacc +1\njmp -1").unwrap().len(), 3);
+    assert_eq!(assemble("a: b: nop a\njmp b").unwrap()[1].arg, -1);
+}
+
+#[test]
+fn test_assemble_errors() {
+    let err = |src: &str| assemble(src).unwrap_err().to_string();
+    assert_eq!(err("x: nop +0\nx: nop +0"), "line 2: duplicate label x");
+    assert_eq!(err("nop +0\njmp nowhere"), "line 2: undefined label nowhere");
+    assert_eq!(err("1x: nop +0"), "line 1: bad label \"1x\"");
+    assert_eq!(err("x: acc x"), "line 1: acc can't take a label");
+    assert_eq!(err("nop +0\n\nfoo +1"), "line 3: foo +1");
+}
+
+#[test]
+fn test_round_trip() {
+    let progs = [
+        "nop +0\nacc +1\njmp +4\nacc +3\njmp -3\nacc -99\nacc +1\njmp -4\nacc +6",
+        "acc b +3\nacc +2\nacc b -1\njnz b -2\njz a +2\nacc +100",
+        "jmp +0\njmp -5\njmp +7\nnop +1",
+    ];
+    for src in progs {
+        let prog = assemble(src).unwrap();
+        let text = disassemble(&prog);
+        assert_eq!(assemble(&text).unwrap(), prog, "{}", text);
+    }
+
+    let text = disassemble(&assemble(progs[1]).unwrap());
+    assert_eq!(text, "    acc b +3
+L1:
+    acc +2
+    acc b -1
+    jnz b L1
+    jz a L6
+    acc +100
+L6:
+");
+}
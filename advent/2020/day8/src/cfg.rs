@@ -0,0 +1,150 @@
+use std::collections::VecDeque;
+
+use crate::vm::{Instr, Op, Vm, Stop};
+
+fn jump_target(pc: usize, instr: Instr, len: usize) -> Option<usize> {
+    pc.checked_add_signed(instr.arg as isize).filter(|&next| next <= len)
+}
+
+// where control can go after running instr at pc, leaving out anywhere outside the program
+fn successors(pc: usize, instr: Instr, len: usize) -> Vec<usize> {
+    let jump = jump_target(pc, instr, len);
+    match instr.op {
+        Op::Nop | Op::Acc(_) => vec![pc + 1],
+        Op::Jmp => jump.into_iter().collect(),
+        Op::Jz(_) | Op::Jnz(_) => [Some(pc + 1), jump].iter().flatten().copied().collect(),
+    }
+}
+
+// node prog.len() is the exit, only conditional jumps have more than one successor
+pub struct Cfg {
+    pub succ: Vec<Vec<usize>>,
+    pub preds: Vec<Vec<usize>>,
+}
+
+impl Cfg {
+    pub fn new(prog: &[Instr]) -> Cfg {
+        let len = prog.len();
+        let succ = prog.iter().enumerate().map(|(pc, &instr)| successors(pc, instr, len)).collect::<Vec<_>>();
+        let mut preds = vec![Vec::new(); len + 1];
+        for (pc, nexts) in succ.iter().enumerate() {
+            for &next in nexts {
+                preds[next].push(pc);
+            }
+        }
+        Cfg { succ, preds }
+    }
+
+    pub fn exit(&self) -> usize {
+        self.succ.len()
+    }
+
+    // every node that can end up at the exit, found by walking preds back from it
+    pub fn reaches_exit(&self) -> Vec<bool> {
+        let mut seen = vec![false; self.exit() + 1];
+        let mut queue = VecDeque::from([self.exit()]);
+        seen[self.exit()] = true;
+        while let Some(node) = queue.pop_front() {
+            for &pred in &self.preds[node] {
+                if !seen[pred] {
+                    seen[pred] = true;
+                    queue.push_back(pred);
+                }
+            }
+        }
+        seen
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Fix {
+    pub index: usize,
+    pub patched: Instr,
+    // only the tail of it if the run was longer than the vm keeps a trace for
+    pub path: Vec<usize>,
+    pub steps: usize,
+    pub acc: i32,
+}
+
+fn run_patched(prog: &[Instr], index: usize, patched: Instr) -> Option<Fix> {
+    let mut vm = Vm::new(prog.to_vec());
+    vm.prog[index] = patched;
+    if vm.run() != Stop::Terminated {
+        return None;
+    }
+    Some(Fix {
+        index,
+        patched,
+        path: vm.trace.iter().map(|s| s.pc).collect(),
+        steps: vm.steps,
+        acc: vm.acc,
+    })
+}
+
+// The instructions run from 0 before looping can't reach the exit, so none of them
+// sit on the path after the flip. That means flipping one of them fixes the program
+// exactly when its new target already reaches the exit in the unpatched graph, and
+// the first candidate found is the answer. Conditional jumps make reaching the exit
+// only a possibility, so each candidate still gets run to be sure.
+pub fn find_fix(prog: &[Instr]) -> Option<Fix> {
+    let cfg = Cfg::new(prog);
+    let good = cfg.reaches_exit();
+
+    // the trace can be cut short on long conditional runs, but the set of pcs run can't
+    let mut vm = Vm::new(prog.to_vec());
+    vm.run();
+    (0..prog.len()).filter(|pc| vm.run.contains(pc)).find_map(|pc| {
+        let patched = prog[pc].flipped()?;
+        let good = successors(pc, patched, prog.len()).iter().any(|&next| good[next]);
+        if good { run_patched(prog, pc, patched) } else { None }
+    })
+}
+
+#[test]
+fn test_reaches_exit() {
+    let prog = ["nop +0", "acc +1", "jmp +4", "acc +3", "jmp -3", "acc -99", "acc +1", "jmp -4", "acc +6"]
+        .iter().map(|l| l.parse()).collect::<anyhow::Result<Vec<Instr>>>().unwrap();
+    let cfg = Cfg::new(&prog);
+    assert_eq!(cfg.succ[7], vec![3]);
+    assert_eq!(cfg.preds[3], vec![7]);
+    let good = cfg.reaches_exit();
+    assert_eq!((0..good.len()).filter(|&i| good[i]).collect::<Vec<_>>(), vec![8, 9]);
+
+    let fix = find_fix(&prog).unwrap();
+    assert_eq!(fix.index, 7);
+    assert_eq!(fix.patched.op, Op::Nop);
+    assert_eq!(fix.path, vec![0, 1, 2, 6, 7, 8]);
+    assert_eq!(fix.acc, 8);
+}
+
+#[test]
+fn test_no_fix() {
+    let prog = ["acc +1", "acc +2", "jmp -2", "jmp -1"].iter().map(|l| l.parse()).collect::<anyhow::Result<Vec<Instr>>>().unwrap();
+    assert_eq!(find_fix(&prog), None);
+    let prog = ["jmp -5", "acc +1"].iter().map(|l| l.parse()).collect::<anyhow::Result<Vec<Instr>>>().unwrap();
+    assert_eq!(Cfg::new(&prog).succ[0], vec![]);
+    assert_eq!(find_fix(&prog).unwrap().index, 0);
+}
+
+#[test]
+fn test_conditional() {
+    let prog = ["acc b +2", "acc b -1", "jnz b -1", "jmp -3"]
+        .iter().map(|l| l.parse()).collect::<anyhow::Result<Vec<Instr>>>().unwrap();
+    let cfg = Cfg::new(&prog);
+    assert_eq!(cfg.succ[2], vec![3, 1]);
+    assert_eq!(cfg.preds[1], vec![0, 2]);
+    assert!(!cfg.reaches_exit()[0]);
+    let fix = find_fix(&prog).unwrap();
+    assert_eq!(fix.index, 3);
+    assert_eq!(fix.path, vec![0, 1, 2, 1, 2, 3]);
+}
+
+#[test]
+fn test_runaway() {
+    // the unpatched program counts b up forever from pc 1
+    let prog = ["nop +2", "acc b +1", "jnz b -1"]
+        .iter().map(|l| l.parse()).collect::<anyhow::Result<Vec<Instr>>>().unwrap();
+    let fix = find_fix(&prog).unwrap();
+    assert_eq!(fix.index, 0);
+    assert_eq!((fix.path, fix.steps), (vec![0, 2], 2));
+}
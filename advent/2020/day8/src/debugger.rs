@@ -0,0 +1,195 @@
+use std::{io::{BufRead, Write}, str::FromStr};
+
+use anyhow::{Result, anyhow};
+use itertools::Itertools;
+
+use crate::vm::{Vm, Stop};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Cmd {
+    Step(usize),
+    Continue,
+    Break(usize),
+    Delete(usize),
+    Watch,
+    Print,
+    Visited,
+    Trace(usize),
+    List,
+    Flip(usize),
+    Reset,
+    Help,
+    Quit,
+}
+
+impl FromStr for Cmd {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut words = s.split_whitespace();
+        let cmd = words.next().ok_or(anyhow!("empty command"))?;
+        let arg = words.next().map(|a| a.parse::<usize>()).transpose()?;
+        let need_arg = || arg.ok_or(anyhow!("{} needs an argument", cmd));
+        Ok(match cmd {
+            "s" | "step" => Cmd::Step(arg.unwrap_or(1)),
+            "c" | "continue" => Cmd::Continue,
+            "b" | "break" => Cmd::Break(need_arg()?),
+            "d" | "delete" => Cmd::Delete(need_arg()?),
+            "w" | "watch" => Cmd::Watch,
+            "p" | "print" => Cmd::Print,
+            "v" | "visited" => Cmd::Visited,
+            "t" | "trace" => Cmd::Trace(arg.unwrap_or(10)),
+            "l" | "list" => Cmd::List,
+            "f" | "flip" => Cmd::Flip(need_arg()?),
+            "r" | "reset" => Cmd::Reset,
+            "h" | "help" => Cmd::Help,
+            "q" | "quit" => Cmd::Quit,
+            _ => return Err(anyhow!("unknown command {}", cmd))
+        })
+    }
+}
+
+const HELP: &str = "\
+s|step [n]     run n instructions (default 1)
+c|continue     run until a breakpoint, watch, loop or termination
+b|break <pc>   set a breakpoint
+d|delete <pc>  remove a breakpoint
+w|watch        toggle stopping whenever acc changes
+p|print        show pc, acc and the current instruction
+v|visited      show the visited set
+t|trace [n]    show the last n executed instructions (default 10)
+l|list         show the program around pc
+f|flip <pc>    swap jmp/nop at pc and reset
+r|reset        restart the program
+q|quit         leave";
+
+fn report(vm: &Vm, stop: Option<Stop>, out: &mut impl Write) -> Result<()> {
+    match stop {
+        None => (),
+        Some(Stop::Terminated) => writeln!(out, "terminated with acc {}", vm.acc)?,
+        Some(Stop::Loop) => writeln!(out, "loop: pc {} already run, acc {}", vm.pc, vm.acc)?,
+        Some(Stop::OutOfBounds) => writeln!(out, "pc out of bounds after {}", vm.trace.back().map_or(0, |s| s.pc))?,
+        Some(Stop::Breakpoint) => writeln!(out, "breakpoint at {}", vm.pc)?,
+        Some(Stop::Watch { old, new }) => writeln!(out, "acc changed {} -> {}", old, new)?,
+        Some(Stop::Overflow) => writeln!(out, "overflow: {} at {} would overflow a register", vm.prog[vm.pc], vm.pc)?,
+        Some(Stop::StepLimit) => writeln!(out, "gave up after {} steps", vm.steps)?,
+    }
+    print_state(vm, out)
+}
+
+fn print_state(vm: &Vm, out: &mut impl Write) -> Result<()> {
+    match vm.prog.get(vm.pc) {
+        Some(instr) => writeln!(out, "pc: {} acc: {} b: {} next: {}", vm.pc, vm.acc, vm.b, instr)?,
+        None => writeln!(out, "pc: {} acc: {} b: {}", vm.pc, vm.acc, vm.b)?,
+    }
+    Ok(())
+}
+
+fn list(vm: &Vm, out: &mut impl Write) -> Result<()> {
+    let start = vm.pc.saturating_sub(5);
+    let end = (vm.pc + 6).min(vm.prog.len());
+    for i in start..end {
+        let marker = if i == vm.pc { '>' } else { ' ' };
+        let bp = if vm.breakpoints.contains(&i) { '*' } else { ' ' };
+        let seen = if vm.run.contains(&i) { "(run)" } else { "" };
+        writeln!(out, "{}{}{:5} {} {}", marker, bp, i, vm.prog[i], seen)?;
+    }
+    Ok(())
+}
+
+fn exec(vm: &mut Vm, cmd: Cmd, out: &mut impl Write) -> Result<()> {
+    match cmd {
+        Cmd::Step(n) => {
+            let mut stop = None;
+            for _ in 0..n {
+                stop = vm.step();
+                if stop.is_some() {
+                    break;
+                }
+            }
+            report(vm, stop, out)?;
+        },
+        Cmd::Continue => {
+            let stop = vm.run();
+            report(vm, Some(stop), out)?;
+        },
+        Cmd::Break(pc) => {
+            vm.breakpoints.insert(pc);
+        },
+        Cmd::Delete(pc) => {
+            vm.breakpoints.remove(&pc);
+        },
+        Cmd::Watch => {
+            vm.watch_acc = !vm.watch_acc;
+            writeln!(out, "watching acc: {}", vm.watch_acc)?;
+        },
+        Cmd::Print => print_state(vm, out)?,
+        Cmd::Visited => writeln!(out, "{} run: {}", vm.run.len(), vm.run.iter().sorted().join(" "))?,
+        Cmd::Trace(n) => {
+            for step in vm.trace.iter().rev().take(n).rev() {
+                writeln!(out, "{:5} {} acc: {} b: {}", step.pc, step.instr, step.acc, step.b)?;
+            }
+        },
+        Cmd::List => list(vm, out)?,
+        Cmd::Flip(pc) => {
+            let Some(instr) = vm.prog.get(pc) else {
+                return Err(anyhow!("no instr at {}", pc));
+            };
+            let flipped = instr.flipped().ok_or(anyhow!("can't flip {}", instr))?;
+            writeln!(out, "{}: {} -> {}", pc, instr, flipped)?;
+            vm.prog[pc] = flipped;
+            vm.reset();
+        },
+        Cmd::Reset => vm.reset(),
+        Cmd::Help => writeln!(out, "{}", HELP)?,
+        Cmd::Quit => (),
+    }
+    Ok(())
+}
+
+pub fn repl(vm: &mut Vm, input: impl BufRead, mut out: impl Write) -> Result<()> {
+    print_state(vm, &mut out)?;
+    write!(out, "> ")?;
+    out.flush()?;
+    for line in input.lines() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            match line.parse::<Cmd>() {
+                Ok(Cmd::Quit) => break,
+                Ok(cmd) => {
+                    if let Err(e) = exec(vm, cmd, &mut out) {
+                        writeln!(out, "error: {}", e)?;
+                    }
+                },
+                Err(e) => writeln!(out, "error: {}", e)?,
+            }
+        }
+        write!(out, "> ")?;
+        out.flush()?;
+    }
+    Ok(())
+}
+
+#[test]
+fn test_cmd_parse() {
+    assert_eq!("s".parse::<Cmd>().unwrap(), Cmd::Step(1));
+    assert_eq!("step 4".parse::<Cmd>().unwrap(), Cmd::Step(4));
+    assert_eq!("b 12".parse::<Cmd>().unwrap(), Cmd::Break(12));
+    assert!("b".parse::<Cmd>().is_err());
+    assert!("b x".parse::<Cmd>().is_err());
+    assert!("frob".parse::<Cmd>().is_err());
+}
+
+#[test]
+fn test_repl() {
+    let prog = "nop +0\nacc +1\njmp +4\nacc +3\njmp -3\nacc -99\nacc +1\njmp -4\nacc +6"
+        .lines().map(|l| l.parse()).collect::<Result<Vec<_>>>().unwrap();
+    let mut vm = Vm::new(prog);
+    let mut out = Vec::new();
+    repl(&mut vm, "b 7\nc\nv\nd 7\nf 7\nc\nq\ns\n".as_bytes(), &mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.contains("breakpoint at 7"));
+    assert!(out.contains("4 run: 0 1 2 6"));
+    assert!(out.contains("7: jmp -4 -> nop -4"));
+    assert!(out.contains("terminated with acc 8"));
+    assert_eq!(vm.acc, 8);
+}
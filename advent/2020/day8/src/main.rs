@@ -1,85 +1,38 @@
-use std::{str::FromStr, collections::HashSet};
+mod vm;
+mod debugger;
+mod cfg;
+mod asm;
 
-use anyhow::{Result, anyhow};
+use anyhow::Result;
+use itertools::Itertools;
 
-#[derive(Clone, Debug)]
-enum Op {
-    Nop,
-    Acc,
-    Jmp,
-}
-
-#[derive(Clone, Debug)]
-struct Instr {
-    op: Op,
-    arg: i32,
-}
-
-impl FromStr for Instr {
-    type Err = anyhow::Error;
-    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
-        let (op, arg) = s.split_once(' ').ok_or(anyhow!("bad instr {}", s))?;
-        Ok(Instr {
-            op: match op {
-                "nop" => Op::Nop,
-                "acc" => Op::Acc,
-                "jmp" => Op::Jmp,
-                _ => return Err(anyhow!("bad op {}", op))
-            },
-            arg: arg.parse()?
-        })
-    }
-}
+use vm::Vm;
 
-struct State {
-    pc: usize,
-    acc: i32,
-    run: HashSet<usize>
-}
-
-impl State {
-    fn run(&mut self, prog: &Vec<Instr>) -> bool {
-        loop {
-            if self.run.contains(&self.pc) {
-                return false;
-            }
-            if self.pc == prog.len() {
-                return true;
-            }
-            let instr = &prog[self.pc];
-            self.run.insert(self.pc);
-            match instr.op {
-                Op::Nop => self.pc += 1,
-                Op::Acc => {
-                    self.acc += instr.arg;
-                    self.pc += 1;
-                },
-                Op::Jmp => self.pc = (self.pc as i32 + instr.arg) as usize,
-            }
-        }
+fn main() -> Result<()> {
+    let args = std::env::args().collect::<Vec<_>>();
+    let path = args.get(2).map_or("input.txt", |p| p.as_str());
+    let input = std::fs::read_to_string(path)?;
+    let prog = asm::assemble(&input)?;
+
+    match args.get(1).map(|a| a.as_str()) {
+        Some("debug") => {
+            let mut vm = Vm::new(prog);
+            return debugger::repl(&mut vm, std::io::stdin().lock(), std::io::stdout());
+        },
+        Some("disasm") => {
+            print!("{}", asm::disassemble(&prog));
+            return Ok(());
+        },
+        _ => (),
     }
-}
 
-fn main() -> Result<()> {
-    let input = std::fs::read_to_string("input.txt")?;
-    let prog = input.lines().map(|l| l.parse::<Instr>()).collect::<Result<Vec<_>>>()?;
-    for i in 0..prog.len() {
-        println!("instr was {:?}", prog[i]);
-        let mut prog = prog.clone();
-        let instr = prog.get_mut(i).unwrap();
-        match instr.op {
-            Op::Acc => continue,
-            Op::Jmp => instr.op = Op::Nop,
-            Op::Nop => instr.op = Op::Jmp
-        }
-        println!("instr changed to {:?}", prog[i]);
-        let mut state = State { pc: 0, acc: 0, run: HashSet::new() };
-        if state.run(&prog) {
-            println!("terminated after changing instr {} with {}", i, state.acc);
-            break;
-        } else {
-            println!("loop after changing instr {} pc: {}", i, state.pc);
-        }
+    match cfg::find_fix(&prog) {
+        Some(fix) => {
+            println!("changing instr {} from {} to {}", fix.index, prog[fix.index], fix.patched);
+            println!("path: {}", fix.path.iter().join(" "));
+            println!("terminated after {} instrs with {}", fix.steps, fix.acc);
+        },
+        None => println!("no single jmp/nop flip terminates"),
     }
 
     Ok(())
@@ -0,0 +1,379 @@
+use std::{str::FromStr, collections::{HashSet, VecDeque}, fmt};
+
+use anyhow::anyhow;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reg {
+    A,
+    B,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op {
+    Nop,
+    Acc(Reg),
+    Jmp,
+    Jz(Reg),
+    Jnz(Reg),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Instr {
+    pub op: Op,
+    pub arg: i32,
+}
+
+impl Instr {
+    // the jmp <-> nop swap the puzzle is about, nothing else can be patched
+    pub fn flipped(&self) -> Option<Instr> {
+        let op = match self.op {
+            Op::Nop => Op::Jmp,
+            Op::Jmp => Op::Nop,
+            _ => return None,
+        };
+        Some(Instr { op, arg: self.arg })
+    }
+
+    // ops whose arg is a relative jump target
+    pub fn is_jump(&self) -> bool {
+        matches!(self.op, Op::Jmp | Op::Jz(_) | Op::Jnz(_))
+    }
+}
+
+impl FromStr for Reg {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "a" => Ok(Reg::A),
+            "b" => Ok(Reg::B),
+            _ => Err(anyhow!("bad register {}", s))
+        }
+    }
+}
+
+impl FromStr for Instr {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let words = s.split_whitespace().collect::<Vec<_>>();
+        let (op, reg, arg) = match words[..] {
+            [op, arg] => (op, None, arg),
+            [op, reg, arg] => (op, Some(reg.parse::<Reg>()?), arg),
+            _ => return Err(anyhow!("bad instr {}", s))
+        };
+        Ok(Instr {
+            op: match (op, reg) {
+                ("nop", None) => Op::Nop,
+                ("acc", reg) => Op::Acc(reg.unwrap_or(Reg::A)),
+                ("jmp", None) => Op::Jmp,
+                ("jz", Some(reg)) => Op::Jz(reg),
+                ("jnz", Some(reg)) => Op::Jnz(reg),
+                _ => return Err(anyhow!("bad op {}", s))
+            },
+            arg: arg.parse()?
+        })
+    }
+}
+
+impl fmt::Display for Reg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reg::A => write!(f, "a"),
+            Reg::B => write!(f, "b"),
+        }
+    }
+}
+
+// everything but the arg, so the assembler can print a label there instead
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Op::Nop => write!(f, "nop"),
+            Op::Acc(Reg::A) => write!(f, "acc"),
+            Op::Acc(reg) => write!(f, "acc {}", reg),
+            Op::Jmp => write!(f, "jmp"),
+            Op::Jz(reg) => write!(f, "jz {}", reg),
+            Op::Jnz(reg) => write!(f, "jnz {}", reg),
+        }
+    }
+}
+
+impl fmt::Display for Instr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {:+}", self.op, self.arg)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stop {
+    Terminated,
+    // pc is about to run an instruction for the second time, or the whole state repeats
+    Loop,
+    // pc went somewhere other than the program or one past its end
+    OutOfBounds,
+    Breakpoint,
+    Watch { old: i32, new: i32 },
+    // an acc would take a register past what an i32 holds
+    Overflow,
+    // ran step_limit instructions without stopping any other way
+    StepLimit,
+}
+
+// Conditional programs only loop once a whole state repeats, which can take
+// billions of steps, so runs give up after this many by default.
+pub const MAX_STEPS: usize = 1_000_000;
+// the most recent steps kept in the trace, unless the program is longer than this
+pub const TRACE_LEN: usize = 10_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Step {
+    pub pc: usize,
+    pub instr: Instr,
+    pub acc: i32,
+    pub b: i32,
+}
+
+#[derive(Clone, Debug)]
+pub struct Vm {
+    pub prog: Vec<Instr>,
+    pub pc: usize,
+    pub acc: i32,
+    pub b: i32,
+    pub run: HashSet<usize>,
+    // with conditional jumps a revisited pc isn't necessarily a loop, so those
+    // programs only loop once the whole machine state repeats
+    pub conditional: bool,
+    pub states: HashSet<(usize, i32, i32)>,
+    pub breakpoints: HashSet<usize>,
+    pub watch_acc: bool,
+    pub steps: usize,
+    pub step_limit: usize,
+    // the last trace_len steps, which is every step for programs without
+    // conditional jumps since those run each instruction at most once
+    pub trace: VecDeque<Step>,
+    pub trace_len: usize,
+}
+
+impl Vm {
+    pub fn new(prog: Vec<Instr>) -> Vm {
+        Vm {
+            conditional: prog.iter().any(|i| matches!(i.op, Op::Jz(_) | Op::Jnz(_))),
+            trace_len: TRACE_LEN.max(prog.len()),
+            prog,
+            pc: 0,
+            acc: 0,
+            b: 0,
+            run: HashSet::new(),
+            states: HashSet::new(),
+            breakpoints: HashSet::new(),
+            watch_acc: false,
+            steps: 0,
+            step_limit: MAX_STEPS,
+            trace: VecDeque::new(),
+        }
+    }
+
+    // back to the start, but keep breakpoints and watches around
+    pub fn reset(&mut self) {
+        self.pc = 0;
+        self.acc = 0;
+        self.b = 0;
+        self.steps = 0;
+        self.run.clear();
+        self.states.clear();
+        self.trace.clear();
+    }
+
+    pub fn reg(&self, reg: Reg) -> i32 {
+        match reg {
+            Reg::A => self.acc,
+            Reg::B => self.b,
+        }
+    }
+
+    fn reg_mut(&mut self, reg: Reg) -> &mut i32 {
+        match reg {
+            Reg::A => &mut self.acc,
+            Reg::B => &mut self.b,
+        }
+    }
+
+    fn jump(&mut self, arg: i32) -> Option<Stop> {
+        match self.pc.checked_add_signed(arg as isize) {
+            Some(pc) => {
+                self.pc = pc;
+                None
+            },
+            None => Some(Stop::OutOfBounds),
+        }
+    }
+
+    pub fn halted(&self) -> Option<Stop> {
+        if self.pc == self.prog.len() {
+            Some(Stop::Terminated)
+        } else if self.pc > self.prog.len() {
+            Some(Stop::OutOfBounds)
+        } else if self.run.contains(&self.pc)
+            && (!self.conditional || self.states.contains(&(self.pc, self.acc, self.b))) {
+            Some(Stop::Loop)
+        } else {
+            None
+        }
+    }
+
+    pub fn step(&mut self) -> Option<Stop> {
+        if let Some(stop) = self.halted() {
+            return Some(stop);
+        }
+        if self.steps >= self.step_limit {
+            return Some(Stop::StepLimit);
+        }
+        let instr = self.prog[self.pc];
+        if let Op::Acc(reg) = instr.op {
+            if self.reg(reg).checked_add(instr.arg).is_none() {
+                return Some(Stop::Overflow);
+            }
+        }
+        let old = self.acc;
+        self.run.insert(self.pc);
+        if self.conditional {
+            self.states.insert((self.pc, self.acc, self.b));
+        }
+        self.steps += 1;
+        if self.trace.len() == self.trace_len {
+            self.trace.pop_front();
+        }
+        self.trace.push_back(Step { pc: self.pc, instr, acc: old, b: self.b });
+        let taken = match instr.op {
+            Op::Nop => false,
+            Op::Acc(reg) => {
+                *self.reg_mut(reg) += instr.arg;
+                false
+            },
+            Op::Jmp => true,
+            Op::Jz(reg) => self.reg(reg) == 0,
+            Op::Jnz(reg) => self.reg(reg) != 0,
+        };
+        if !taken {
+            self.pc += 1;
+        } else if let Some(stop) = self.jump(instr.arg) {
+            return Some(stop);
+        }
+        if self.watch_acc && self.acc != old {
+            return Some(Stop::Watch { old, new: self.acc });
+        }
+        None
+    }
+
+    pub fn run(&mut self) -> Stop {
+        loop {
+            if let Some(stop) = self.step() {
+                return stop;
+            }
+            if self.breakpoints.contains(&self.pc) {
+                return Stop::Breakpoint;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+fn parse(src: &str) -> Vec<Instr> {
+    src.lines().map(|l| l.parse()).collect::<anyhow::Result<Vec<_>>>().unwrap()
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = "nop +0
+acc +1
+jmp +4
+acc +3
+jmp -3
+acc -99
+acc +1
+jmp -4
+acc +6";
+
+#[test]
+fn test_run() {
+    let mut vm = Vm::new(parse(EXAMPLE));
+    assert_eq!(vm.run(), Stop::Loop);
+    assert_eq!(vm.pc, 1);
+    assert_eq!(vm.acc, 5);
+    assert_eq!(vm.trace.iter().map(|s| s.pc).collect::<Vec<_>>(), vec![0, 1, 2, 6, 7, 3, 4]);
+
+    let mut prog = parse(EXAMPLE);
+    prog[7] = prog[7].flipped().unwrap();
+    let mut vm = Vm::new(prog);
+    assert_eq!(vm.run(), Stop::Terminated);
+    assert_eq!(vm.acc, 8);
+}
+
+#[test]
+fn test_breakpoints_and_watch() {
+    let mut vm = Vm::new(parse(EXAMPLE));
+    vm.breakpoints.insert(6);
+    assert_eq!(vm.run(), Stop::Breakpoint);
+    assert_eq!((vm.pc, vm.acc), (6, 1));
+    assert!(vm.run.contains(&2));
+
+    vm.watch_acc = true;
+    assert_eq!(vm.run(), Stop::Watch { old: 1, new: 2 });
+    assert_eq!(vm.pc, 7);
+
+    vm.reset();
+    assert_eq!(vm.step(), None);
+    assert_eq!(vm.step(), Some(Stop::Watch { old: 0, new: 1 }));
+    assert_eq!(vm.trace.len(), 2);
+}
+
+#[test]
+fn test_registers() {
+    // count b down from 3, adding 2 to a each time round
+    let mut vm = Vm::new(parse("acc b +3\nacc +2\nacc b -1\njnz b -2\njnz a +2\nacc +100"));
+    assert_eq!(vm.run(), Stop::Terminated);
+    assert_eq!((vm.acc, vm.b), (6, 0));
+    assert_eq!(vm.trace.len(), 11);
+
+    let mut vm = Vm::new(parse("acc b +1\njnz b +0"));
+    assert_eq!(vm.run(), Stop::Loop);
+    assert_eq!((vm.pc, vm.b), (1, 1));
+}
+
+#[test]
+fn test_parse() {
+    assert_eq!("acc +3".parse::<Instr>().unwrap(), Instr { op: Op::Acc(Reg::A), arg: 3 });
+    assert_eq!("acc b -3".parse::<Instr>().unwrap(), Instr { op: Op::Acc(Reg::B), arg: -3 });
+    assert_eq!("jnz a +0".parse::<Instr>().unwrap(), Instr { op: Op::Jnz(Reg::A), arg: 0 });
+    assert!("jz +2".parse::<Instr>().is_err());
+    assert!("jmp b +2".parse::<Instr>().is_err());
+    assert!("acc c +2".parse::<Instr>().is_err());
+    for s in ["nop +0", "acc -7", "acc b +1", "jmp -3", "jz b +2", "jnz a -1"] {
+        assert_eq!(s.parse::<Instr>().unwrap().to_string(), s);
+    }
+}
+
+#[test]
+fn test_out_of_bounds() {
+    let mut vm = Vm::new(parse("nop +0\njmp -2"));
+    assert_eq!(vm.run(), Stop::OutOfBounds);
+    let mut vm = Vm::new(parse("jmp +5"));
+    assert_eq!(vm.run(), Stop::OutOfBounds);
+    assert_eq!(vm.step(), Some(Stop::OutOfBounds));
+}
+
+#[test]
+fn test_runaway() {
+    // b counts up forever, so the state never repeats
+    let mut vm = Vm::new(parse("acc b +1\njnz b -1"));
+    assert_eq!(vm.run(), Stop::StepLimit);
+    assert_eq!(vm.steps, MAX_STEPS);
+    assert_eq!(vm.trace.len(), TRACE_LEN);
+    assert_eq!(vm.trace.back().unwrap().b, MAX_STEPS as i32 / 2);
+    assert_eq!(vm.step(), Some(Stop::StepLimit));
+
+    let mut vm = Vm::new(parse("acc b +2147483646\nacc b +1\njnz b -1"));
+    assert_eq!(vm.run(), Stop::Overflow);
+    assert_eq!((vm.pc, vm.b, vm.steps), (1, i32::MAX, 3));
+    let mut vm = Vm::new(parse("acc -2147483648\nacc -1"));
+    assert_eq!(vm.run(), Stop::Overflow);
+    assert_eq!(vm.acc, i32::MIN);
+}